@@ -0,0 +1,102 @@
+use std::io::Write;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame};
+
+use crate::error::VtfxError;
+
+///Frame rate used by the AnimatedTexture material proxy when none is set
+pub const DEFAULT_FRAME_RATE: f32 = 15.0;
+
+///Encode frames as a looping animated gif
+pub fn write_gif<W: Write>(frames: &[DynamicImage], frame_rate: f32, writer: W) -> Result<(), VtfxError>
+{
+    let delay_ms = (1000.0 / frame_rate.max(0.001)).round() as u32;
+    let mut encoder = GifEncoder::new(writer);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let gif_frames = frames.iter()
+        .map(|frame| Frame::from_parts(frame.to_rgba8(), 0, 0, Delay::from_numer_denom_ms(delay_ms, 1)));
+    encoder.encode_frames(gif_frames)?;
+    Ok(())
+}
+
+///Encode frames as a looping animated png. All frames must be the same size.
+pub fn write_apng<W: Write>(frames: &[DynamicImage], frame_rate: f32, writer: W) -> Result<(), VtfxError>
+{
+    let Some(first_frame) = frames.first() else {
+        return Ok(());
+    };
+
+    let mut encoder = png::Encoder::new(writer, first_frame.width(), first_frame.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    //Delay as fraction of a second (1 / frame rate), scaled to keep precision for non integer rates
+    encoder.set_frame_delay(1000, (frame_rate * 1000.0).round().clamp(1.0, u16::MAX as f32) as u16)?;
+
+    let mut png_writer = encoder.write_header()?;
+    for frame in frames
+    {
+        png_writer.write_image_data(frame.to_rgba8().as_raw())?;
+    }
+    png_writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::{AnimationDecoder, Rgba, RgbaImage};
+
+    ///2x2 frames where red is the frame number
+    fn numbered_frames(count: u8) -> Vec<DynamicImage>
+    {
+        (0..count).map(|frame| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([frame * 50, 0, 0, 255])))).collect()
+    }
+
+    #[test]
+    fn gif_has_every_frame_in_order()
+    {
+        let mut gif = Vec::new();
+        write_gif(&numbered_frames(3), 10.0, &mut gif).unwrap();
+
+        let frames = GifDecoder::new(&gif[..]).unwrap().into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        for (frame_num, frame) in frames.iter().enumerate()
+        {
+            assert_eq!(frame.buffer().get_pixel(0, 0).0, [frame_num as u8 * 50, 0, 0, 255], "frame {}", frame_num);
+            assert_eq!(frame.delay().numer_denom_ms(), (100, 1));
+        }
+    }
+
+    #[test]
+    fn apng_has_every_frame_in_order()
+    {
+        let mut apng = Vec::new();
+        write_apng(&numbered_frames(3), 15.0, &mut apng).unwrap();
+
+        let mut reader = png::Decoder::new(&apng[..]).read_info().unwrap();
+        let animation = reader.info().animation_control.unwrap();
+        assert_eq!((animation.num_frames, animation.num_plays), (3, 0));
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        for frame_num in 0..3u8
+        {
+            reader.next_frame(&mut buffer).unwrap();
+            let frame_control = reader.info().frame_control.unwrap();
+            assert_eq!((frame_control.delay_num, frame_control.delay_den), (1000, 15000));
+            assert_eq!(buffer[0..4], [frame_num * 50, 0, 0, 255], "frame {}", frame_num);
+        }
+    }
+
+    #[test]
+    fn apng_of_no_frames_is_empty()
+    {
+        let mut apng = Vec::new();
+        write_apng(&[], 15.0, &mut apng).unwrap();
+        assert!(apng.is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use vtfx_reader::animation::DEFAULT_FRAME_RATE;
+use vtfx_reader::hdr::DEFAULT_EXPOSURE;
+use vtfx_reader::vtf_writer::{VTF_MAX_MINOR_VERSION, VTF_MIN_MINOR_VERSION};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = "A tool to parse vtfx files (from x360 and ps3)")]
@@ -21,6 +24,46 @@ pub struct Args {
     #[arg(long, default_value_t = false)]
     pub force_dxt_endian_fix: bool,
 
+    /// Do not untile xbox 360 image resources (otherwise automatic for formats that are not LINEAR)
+    #[arg(long, default_value_t = false)]
+    pub no_untile: bool,
+
+    /// Do not deswizzle ps3 image resources (otherwise automatic for uncompressed formats that are not LINEAR)
+    #[arg(long, default_value_t = false)]
+    pub no_deswizzle: bool,
+
+    /// Calculate the blue (Z) channel of ATI2N normal maps from red (X) and green (Y)
+    #[arg(long, default_value_t = false)]
+    pub reconstruct_z: bool,
+
+    /// Value of blue (0-255) for UV88 images, which only have red (U) and green (V)
+    #[arg(long, default_value_t = 0)]
+    pub uv_blue: u8,
+
+    /// Export luminance formats (I8, IA88) as RGBA instead of grayscale
+    #[arg(long, default_value_t = false)]
+    pub no_grayscale: bool,
+
+    /// Export formats with more than 8 bits per channel (e.g. RGBA16161616) as 8 bit instead of 16 bit
+    #[arg(long, default_value_t = false)]
+    pub eight_bit: bool,
+
+    /// Export signed formats (UVWQ8888, UVLX8888) as raw bytes instead of remapping -128..127 to 0..255
+    #[arg(long, default_value_t = false)]
+    pub raw_signed: bool,
+
+    /// Keep the blue key of BLUESCREEN formats as blue instead of making it transparent
+    #[arg(long, default_value_t = false)]
+    pub keep_bluescreen: bool,
+
+    /// Palette file for P8 images (256 RGB or RGBA colours, e.g. a .act file), required to decode P8 images
+    #[arg(long)]
+    pub palette: Option<PathBuf>,
+
+    /// Export P8 images as indexed pngs with their palette instead of RGBA
+    #[arg(long, default_value_t = false)]
+    pub indexed: bool,
+
     /// Do not export any resources
     #[arg(long, default_value_t = false)]
     pub no_resource_export: bool,
@@ -31,5 +74,138 @@ pub struct Args {
 
     /// EXPERIMENTAL: If alpha export is enabled, force 0 or 255 (inverted).
     #[arg(long, default_value_t = false)]
-    pub experimental_onebitalpha: bool
-}
\ No newline at end of file
+    pub experimental_onebitalpha: bool,
+
+    /// Export all mip levels of image resources (otherwise only the largest is exported)
+    #[arg(long, default_value_t = false, conflicts_with = "mip")]
+    pub all_mips: bool,
+
+    /// Export only this mip level of image resources (0 is the largest)
+    #[arg(long)]
+    pub mip: Option<usize>,
+
+    /// Also export animated textures (more than 1 frame) as an animation
+    #[arg(long, value_enum, default_value_t = AnimationExport::None)]
+    pub animation: AnimationExport,
+
+    /// Frame rate of exported animations
+    #[arg(long, default_value_t = DEFAULT_FRAME_RATE)]
+    pub frame_rate: f32,
+
+    /// How cubemap (envmap) faces are exported
+    #[arg(long, value_enum, default_value_t = CubemapExport::Faces)]
+    pub cubemap: CubemapExport,
+
+    /// How volume texture slices are exported
+    #[arg(long, value_enum, default_value_t = VolumeExport::Strip)]
+    pub volume: VolumeExport,
+
+    /// File format of float (HDR) images
+    #[arg(long, value_enum, default_value_t = HdrExport::Exr)]
+    pub hdr: HdrExport,
+
+    /// Also export float (HDR) images as a tonemapped png
+    #[arg(long, default_value_t = false)]
+    pub tonemap: bool,
+
+    /// Exposure (in stops) of tonemapped pngs
+    #[arg(long, default_value_t = DEFAULT_EXPOSURE, allow_negative_numbers = true)]
+    pub exposure: f32,
+
+    /// Also convert to a PC vtf (lossless, image data is not decoded)
+    #[arg(long, default_value_t = false)]
+    pub vtf: bool,
+
+    /// Minor version of converted PC vtfs (7.2 to 7.5)
+    #[arg(long, default_value_t = VTF_MAX_MINOR_VERSION, value_parser = clap::value_parser!(u32).range(VTF_MIN_MINOR_VERSION as i64..=VTF_MAX_MINOR_VERSION as i64))]
+    pub vtf_version: u32,
+
+    /// Convert input PC vtfs and images (e.g. png) to a vtfx for this platform instead of reading vtfx files
+    #[arg(long, value_enum)]
+    pub to_vtfx: Option<VtfxExport>,
+
+    /// Image format of vtfx files converted from images (PC vtfs keep their format)
+    #[arg(long, value_enum, default_value_t = VtfxImageFormat::Bgra8888)]
+    pub vtfx_format: VtfxImageFormat,
+
+    /// Do not generate mip levels for vtfx files converted from images
+    #[arg(long, default_value_t = false)]
+    pub vtfx_no_mips: bool,
+
+    /// Export the low res (thumbnail) image and low res sample colour
+    #[arg(long, value_enum, default_value_t = LowResExport::None)]
+    pub low_res: LowResExport
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LowResExport
+{
+    /// Do not export the low res image
+    None,
+    /// Export the low res image alongside the main image
+    With,
+    /// Only export the low res image
+    Only
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtfxExport
+{
+    /// Xbox 360 (.360.vtf)
+    X360,
+    /// PS3 (.vtf)
+    Ps3
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtfxImageFormat
+{
+    /// Uncompressed with alpha
+    Bgra8888,
+    /// Uncompressed without alpha
+    Bgr888,
+    /// Compressed without alpha (or 1 bit alpha)
+    Dxt1,
+    /// Compressed with alpha
+    Dxt5
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationExport
+{
+    /// Only export frames as individual images
+    None,
+    /// Animated gif
+    Gif,
+    /// Animated png
+    Apng
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CubemapExport
+{
+    /// Each face as its own image
+    Faces,
+    /// Single image with faces in a horizontal cross
+    Cross,
+    /// Uncompressed dds cubemap
+    Dds
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeExport
+{
+    /// Single image with slices arranged left to right
+    Strip,
+    /// Each slice as its own image
+    Slices
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrExport
+{
+    /// OpenEXR
+    Exr,
+    /// Radiance hdr (no alpha)
+    Hdr
+}
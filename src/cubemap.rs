@@ -0,0 +1,171 @@
+use std::io::Write;
+
+use image::{DynamicImage, GenericImage, GenericImageView};
+use num_enum::TryFromPrimitive;
+
+use crate::error::VtfxError;
+use crate::vtfx::CUBEMAP_FACE_COUNT;
+
+#[derive(Debug, PartialEq, TryFromPrimitive, Eq, Copy, Clone)]
+#[repr(usize)]
+#[allow(non_camel_case_types)] //Keep enums same as source
+//https://github.com/ValveSoftware/source-sdk-2013/blob/master/sp/src/public/vtf/vtf.h
+pub enum CubemapFace
+{
+	CUBEMAP_FACE_RIGHT = 0,
+	CUBEMAP_FACE_LEFT,
+	CUBEMAP_FACE_BACK,	// NOTE: This face is in the +y direction?!?!?
+	CUBEMAP_FACE_FRONT,	// NOTE: This face is in the -y direction!?!?
+	CUBEMAP_FACE_UP,
+	CUBEMAP_FACE_DOWN,
+}
+
+impl CubemapFace
+{
+    ///Short name of face, same as skybox texture suffixes
+    pub fn get_name(&self) -> &'static str
+    {
+        match self
+        {
+            CubemapFace::CUBEMAP_FACE_RIGHT => "rt",
+            CubemapFace::CUBEMAP_FACE_LEFT => "lf",
+            CubemapFace::CUBEMAP_FACE_BACK => "bk",
+            CubemapFace::CUBEMAP_FACE_FRONT => "ft",
+            CubemapFace::CUBEMAP_FACE_UP => "up",
+            CubemapFace::CUBEMAP_FACE_DOWN => "dn",
+        }
+    }
+}
+
+///Position (column, row) of each face in a horizontal cross, in face order (rt, lf, bk, ft, up, dn).
+///Source is Z up, so the middle row is lf, ft, rt, bk with up above and dn below ft.
+const CROSS_LAYOUT: [(u32, u32); CUBEMAP_FACE_COUNT] = [(2, 1), (0, 1), (3, 1), (1, 1), (1, 0), (1, 2)];
+
+///Arrange the 6 cubemap faces into a horizontal cross (4 faces wide, 3 faces tall).
+///Faces are placed as they are stored, without rotation
+pub fn faces_to_cross(faces: &[DynamicImage]) -> Result<DynamicImage, VtfxError>
+{
+    if faces.len() < CUBEMAP_FACE_COUNT
+    {
+        return Err(VtfxError::FaceOutOfRange { face: CUBEMAP_FACE_COUNT - 1, face_count: faces.len() });
+    }
+
+    let (face_width, face_height) = faces[0].dimensions();
+    let mut cross = DynamicImage::new_rgba8(face_width * 4, face_height * 3);
+    for (face, (column, row)) in faces.iter().zip(CROSS_LAYOUT)
+    {
+        cross.copy_from(&face.to_rgba8(), column * face_width, row * face_height)?;
+    }
+
+    Ok(cross)
+}
+
+///Write the 6 cubemap faces as an uncompressed RGBA8 dds cubemap
+pub fn write_dds_cubemap<W: Write>(faces: &[DynamicImage], mut writer: W) -> Result<(), VtfxError>
+{
+    //https://learn.microsoft.com/en-us/windows/win32/direct3ddds/dds-header
+    const DDSD_CAPS: u32 = 0x1;
+    const DDSD_HEIGHT: u32 = 0x2;
+    const DDSD_WIDTH: u32 = 0x4;
+    const DDSD_PITCH: u32 = 0x8;
+    const DDSD_PIXELFORMAT: u32 = 0x1000;
+    const DDPF_ALPHAPIXELS: u32 = 0x1;
+    const DDPF_RGB: u32 = 0x40;
+    const DDSCAPS_COMPLEX: u32 = 0x8;
+    const DDSCAPS_TEXTURE: u32 = 0x1000;
+    const DDSCAPS2_CUBEMAP_ALLFACES: u32 = 0xFE00;
+
+    if faces.len() < CUBEMAP_FACE_COUNT
+    {
+        return Err(VtfxError::FaceOutOfRange { face: CUBEMAP_FACE_COUNT - 1, face_count: faces.len() });
+    }
+
+    let (width, height) = faces[0].dimensions();
+    let mut header: Vec<u32> = vec![
+        124,
+        DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PITCH | DDSD_PIXELFORMAT,
+        height,
+        width,
+        width * 4,
+        0,
+        0,
+    ];
+    //Reserved
+    header.extend_from_slice(&[0; 11]);
+    //Pixel format
+    header.extend_from_slice(&[32, DDPF_RGB | DDPF_ALPHAPIXELS, 0, 32, 0x000000FF, 0x0000FF00, 0x00FF0000, 0xFF000000]);
+    //Caps
+    header.extend_from_slice(&[DDSCAPS_COMPLEX | DDSCAPS_TEXTURE, DDSCAPS2_CUBEMAP_ALLFACES, 0, 0, 0]);
+
+    writer.write_all(b"DDS ")?;
+    for value in header
+    {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    //Faces are stored in the same order as source (+x, -x, +y, -y, +z, -z)
+    for face in &faces[..CUBEMAP_FACE_COUNT]
+    {
+        writer.write_all(face.to_rgba8().as_raw())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    ///1x1 faces where red is the face index
+    fn numbered_faces() -> Vec<DynamicImage>
+    {
+        (0..CUBEMAP_FACE_COUNT as u8).map(|face| DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([face, 0, 0, 255])))).collect()
+    }
+
+    #[test]
+    fn cross_uses_source_axes()
+    {
+        let cross = faces_to_cross(&numbered_faces()).unwrap().to_rgba8();
+        assert_eq!(cross.dimensions(), (4, 3));
+
+        let face_at = |column, row| cross.get_pixel(column, row).0;
+        let middle_row: Vec<&str> = (0..4).map(|column| CubemapFace::try_from(face_at(column, 1)[0] as usize).unwrap().get_name()).collect();
+        assert_eq!(middle_row, ["lf", "ft", "rt", "bk"]);
+        assert_eq!(face_at(1, 0), [CubemapFace::CUBEMAP_FACE_UP as u8, 0, 0, 255]);
+        assert_eq!(face_at(1, 2), [CubemapFace::CUBEMAP_FACE_DOWN as u8, 0, 0, 255]);
+        //Corners are left transparent
+        assert_eq!(face_at(0, 0), [0; 4]);
+        assert_eq!(face_at(3, 2), [0; 4]);
+    }
+
+    #[test]
+    fn cross_needs_every_face()
+    {
+        let faces = numbered_faces();
+        assert!(matches!(faces_to_cross(&faces[..5]), Err(VtfxError::FaceOutOfRange { face: 5, face_count: 5 })));
+    }
+
+    #[test]
+    fn dds_cubemap_has_header_then_faces_in_order()
+    {
+        let mut faces = numbered_faces();
+        faces.iter_mut().for_each(|face| *face = face.resize_exact(2, 1, image::imageops::FilterType::Nearest));
+        let mut dds = Vec::new();
+        write_dds_cubemap(&faces, &mut dds).unwrap();
+
+        let read_u32 = |offset: usize| u32::from_le_bytes(dds[offset..offset + 4].try_into().unwrap());
+        assert_eq!(&dds[0..4], b"DDS ");
+        assert_eq!(read_u32(4), 124);
+        assert_eq!((read_u32(12), read_u32(16), read_u32(20)), (1, 2, 8));
+        //Pixel format is 32 bit RGBA
+        assert_eq!((read_u32(76), read_u32(88), read_u32(92), read_u32(104)), (32, 32, 0xFF, 0xFF000000));
+        assert_eq!((read_u32(108), read_u32(112)), (0x1008, 0xFE00));
+
+        let data = &dds[128..];
+        assert_eq!(data.len(), CUBEMAP_FACE_COUNT * 8);
+        let face_order: Vec<u8> = data.chunks_exact(8).map(|face| face[0]).collect();
+        assert_eq!(face_order, [0, 1, 2, 3, 4, 5]);
+    }
+}
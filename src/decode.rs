@@ -0,0 +1,1189 @@
+use image::DynamicImage;
+use half::f16;
+use image::{GrayAlphaImage, GrayImage, ImageBuffer, Luma, LumaA, Rgba32FImage, RgbaImage};
+
+use crate::error::VtfxError;
+use crate::image_format::{correct_dxt_endianness, image_format_info, DepthFormat, ImageFormat, CHANNEL_NONE};
+use crate::palette::Palette;
+use crate::resource_entry_info::{ResourceEntryInfo, ResourceType};
+use crate::swizzle::{can_swizzle, deswizzle_ps3};
+use crate::untile::{can_tile, get_mip_tail_start, get_packed_mip_offset, get_tiled_size, untile_x360_region};
+use crate::vtfx::{VTFXHEADER, LOW_RES_IMAGE_FORMAT};
+
+pub const LZMA_MAGIC: &[u8;4] = b"LZMA";
+
+///Options that control how image resources are decoded
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DecodeOptions
+{
+    ///If alpha should be included in the decoded image (otherwise alpha is forced to 255)
+    pub export_alpha: bool,
+    ///Force apply big to little endian fix on DXT image resources (otherwise only applied for xbox 360 files)
+    pub force_dxt_endian_fix: bool,
+    ///EXPERIMENTAL: If alpha is exported, force it to 0 or 255 (inverted)
+    pub onebit_alpha: bool,
+    ///Do not untile xbox 360 image resources (otherwise applied for xbox 360 files that are not in a LINEAR format)
+    pub no_untile: bool,
+    ///Calculate the blue (Z) channel of ATI2N normal maps from red (X) and green (Y), otherwise blue is 0
+    pub reconstruct_z: bool,
+    ///Value of blue for UV88 images, which only have red (U) and green (V)
+    pub uv_blue: u8,
+    ///Export luminance formats (I8, IA88) as RGBA instead of grayscale
+    pub no_grayscale: bool,
+    ///Export formats with more than 8 bits per channel as 8 bit RGBA instead of 16 bit
+    pub eight_bit: bool,
+    ///Do not deswizzle ps3 image resources (otherwise applied for uncompressed ps3 resources that are not in a LINEAR format)
+    pub no_deswizzle: bool,
+    ///Keep the raw bytes of signed formats (UVWQ8888, UVLX8888), otherwise they are remapped from -128..127 to 0..255
+    pub raw_signed: bool,
+    ///Keep the blue key of BLUESCREEN formats as is, otherwise pure blue pixels are made transparent (even if alpha is not exported)
+    pub keep_bluescreen: bool,
+    ///Palette of P8 images, otherwise it is found in the vtfx
+    pub palette: Option<Palette>,
+}
+
+///Parse a vtfx file from bytes and decode its first image resource
+pub fn decode_image(buffer: &[u8], options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    let vtfx = VTFXHEADER::from(buffer)?;
+    let resource = vtfx.get_resource_entry_infos(buffer)?
+        .into_iter()
+        .find(|resource| resource.get_type() == ResourceType::VTF_LEGACY_RSRC_IMAGE)
+        .ok_or(VtfxError::NoImageResource)?;
+
+    resource_to_image(buffer, &resource, &vtfx, options)
+}
+
+///Returns true if the resource data at the start of this buffer is lzma compressed
+pub fn is_lzma_compressed(resource_buffer: &[u8]) -> bool
+{
+    resource_buffer.len() >= LZMA_MAGIC.len() && &resource_buffer[0..4] == LZMA_MAGIC
+}
+
+///Extract image resource and return it as DynamicImage (largest mip only)
+pub fn resource_to_image(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, vtfx: &VTFXHEADER, options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    let mip = resource_to_mips(buffer, resource_entry_info, vtfx, options)?.get_mip(0)?;
+    Ok(mip.image)
+}
+
+///Read image resource and return an iterator that decodes each of its mip levels
+pub fn resource_to_mips<'a>(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, vtfx: &'a VTFXHEADER, options: &DecodeOptions) -> Result<MipIterator<'a>, VtfxError>
+{
+    let res_start = resource_entry_info.resData as usize;
+    let format_info = vtfx.image_format.try_get_format_info()?;
+
+    let width = vtfx.width as usize;
+    let height = vtfx.height as usize;
+    let expected_size = format_info.get_expected_size(&width, &height) * vtfx.get_depth();
+    let data = read_resource_data(buffer, res_start, expected_size)?;
+
+    let mut mips = MipIterator {
+        data,
+        vtfx,
+        format_info,
+        options: *options,
+        mip_count: vtfx.mip_count.max(1) as usize,
+        frame_count: vtfx.get_frame_count(),
+        face_count: vtfx.get_face_count(),
+        untile: false,
+        mip_tail_start: None,
+        deswizzle: false,
+        palette: None,
+        level: 0
+    };
+
+    if format_info.paletted
+    {
+        mips.palette = Some(options.palette.ok_or(VtfxError::NoPalette)?);
+    }
+
+    mips.untile = mips.should_untile();
+    if mips.untile
+    {
+        mips.mip_tail_start = get_mip_tail_start(width, height, mips.mip_count);
+    }
+    mips.deswizzle = mips.should_deswizzle();
+    Ok(mips)
+}
+
+///A single decoded mip level of a frame and face
+#[derive(Debug)]
+pub struct MipLevel
+{
+    ///Mip level, 0 is the largest
+    pub level: usize,
+    pub frame: usize,
+    ///Cubemap face (see [`crate::cubemap::CubemapFace`]), 0 if not a cubemap
+    pub face: usize,
+    ///Volume slice, 0 if not a volume texture
+    pub slice: usize,
+    pub width: usize,
+    pub height: usize,
+    pub image: DynamicImage,
+}
+
+///Decodes each mip level of an image resource, starting from the largest (mip 0).
+///Iterating only yields the mips of the first frame, face and slice, use [`MipIterator::get_mip_slice`] for others.
+pub struct MipIterator<'a>
+{
+    data: Vec<u8>,
+    vtfx: &'a VTFXHEADER,
+    format_info: &'static image_format_info,
+    options: DecodeOptions,
+    mip_count: usize,
+    frame_count: usize,
+    face_count: usize,
+    ///If mip data is xbox 360 tiled and needs to be untiled before decoding
+    untile: bool,
+    ///First mip level of tiled mip data that is packed into the mip tail, all packed mips are stored in the surface of this level
+    mip_tail_start: Option<usize>,
+    ///If mip data is ps3 swizzled and needs to be deswizzled before decoding
+    deswizzle: bool,
+    ///Palette of P8 images
+    palette: Option<Palette>,
+    level: usize,
+}
+
+impl MipIterator<'_>
+{
+    pub fn mip_count(&self) -> usize
+    {
+        self.mip_count
+    }
+
+    pub fn frame_count(&self) -> usize
+    {
+        self.frame_count
+    }
+
+    ///Number of faces, 6 for cubemaps otherwise 1
+    pub fn face_count(&self) -> usize
+    {
+        self.face_count
+    }
+
+    ///Number of volume slices in a mip level, 1 if not a volume texture
+    pub fn slice_count(&self, level: usize) -> usize
+    {
+        self.vtfx.get_mip_depth(level)
+    }
+
+    ///Palette of P8 images, None for other formats
+    pub fn palette(&self) -> Option<&Palette>
+    {
+        self.palette.as_ref()
+    }
+
+    ///If mip data is xbox 360 tiled and will be untiled before decoding
+    pub fn is_tiled(&self) -> bool
+    {
+        self.untile
+    }
+
+    ///Xbox 360 resources are tiled unless they are in a LINEAR format. Formats with 24 bit pixels cannot be tiled.
+    ///Volume textures use a 3d tiled layout which is not supported, so are read as linear.
+    fn should_untile(&self) -> bool
+    {
+        let (_, _, block_size) = get_block_layout(1, 1, self.format_info);
+        self.vtfx.is_xbox() && !self.vtfx.image_format.is_linear() && can_tile(block_size) && !self.vtfx.is_volume() && !self.options.no_untile
+    }
+
+    ///If mip data is ps3 swizzled and will be deswizzled before decoding
+    pub fn is_swizzled(&self) -> bool
+    {
+        self.deswizzle
+    }
+
+    ///Uncompressed ps3 resources are swizzled unless they are in a LINEAR format. Only power of 2 sizes can be swizzled.
+    ///Volume textures use a 3d swizzled layout which is not supported, so are read as linear.
+    fn should_deswizzle(&self) -> bool
+    {
+        !self.vtfx.is_xbox() && !self.vtfx.image_format.is_linear() && self.format_info.bc_format.is_none() && can_swizzle(self.vtfx.width as usize, self.vtfx.height as usize) && !self.vtfx.is_volume() && !self.options.no_deswizzle
+    }
+
+    ///Get size of all mips, frames, faces and slices
+    pub fn get_total_size(&self) -> usize
+    {
+        let mips_size: usize = (0..self.mip_count).map(|level| self.get_stored_slice_size(level) * self.slice_count(level)).sum();
+        mips_size * self.frame_count * self.face_count
+    }
+
+    ///Get the mip level whose surface stores a mip level. Tiled mips in the mip tail are stored in the surface of the first packed mip.
+    fn get_surface_level(&self, level: usize) -> usize
+    {
+        match self.mip_tail_start
+        {
+            Some(tail_start) if level > tail_start => tail_start,
+            _ => level
+        }
+    }
+
+    ///Get size of a slice of a mip level as stored in the resource data.
+    ///Tiled surfaces are padded to whole tiles and mips packed into the mip tail take no space of their own.
+    fn get_stored_slice_size(&self, level: usize) -> usize
+    {
+        let (width, height) = self.vtfx.get_mip_size(level);
+        if !self.untile
+        {
+            return self.format_info.get_expected_size(&width, &height);
+        }
+
+        if self.get_surface_level(level) != level
+        {
+            return 0;
+        }
+
+        let (block_width, block_height, block_size) = get_block_layout(width, height, self.format_info);
+        get_tiled_size(block_width, block_height, block_size)
+    }
+
+    ///Get start of a slice of a face of a frame of a mip level in the resource data.
+    ///Each mip level contains all frames, each frame all faces and each face all slices (mip > frame > face > slice), same as PC vtf.
+    ///Mips packed into the mip tail of tiled data start at the surface of the first packed mip.
+    pub fn get_mip_offset(&self, level: usize, frame: usize, face: usize, slice: usize) -> usize
+    {
+        let level = self.get_surface_level(level);
+        let slice_size = self.get_stored_slice_size(level);
+        let face_size = slice_size * self.slice_count(level);
+
+        //Skip all mips stored before this one
+        let mip_start: usize = match self.vtfx.mips_smallest_first()
+        {
+            true => (level + 1..self.mip_count).map(|smaller| self.get_stored_slice_size(smaller) * self.slice_count(smaller)).sum(),
+            false => (0..level).map(|larger| self.get_stored_slice_size(larger) * self.slice_count(larger)).sum()
+        };
+
+        (mip_start * self.frame_count * self.face_count) + (((frame * self.face_count) + face) * face_size) + (slice * slice_size)
+    }
+
+    ///Decode the first frame of a mip level
+    pub fn get_mip(&self, level: usize) -> Result<MipLevel, VtfxError>
+    {
+        self.get_mip_face(level, 0, 0)
+    }
+
+    ///Decode the first face of a frame of a mip level
+    pub fn get_mip_frame(&self, level: usize, frame: usize) -> Result<MipLevel, VtfxError>
+    {
+        self.get_mip_face(level, frame, 0)
+    }
+
+    ///Decode the first slice of a face of a frame of a mip level
+    pub fn get_mip_face(&self, level: usize, frame: usize, face: usize) -> Result<MipLevel, VtfxError>
+    {
+        self.get_mip_slice(level, frame, face, 0)
+    }
+
+    ///Decode a single volume slice of a face of a frame of a mip level
+    pub fn get_mip_slice(&self, level: usize, frame: usize, face: usize, slice: usize) -> Result<MipLevel, VtfxError>
+    {
+        let (width, height) = self.vtfx.get_mip_size(level);
+        let mip_data = self.get_mip_slice_data(level, frame, face, slice)?;
+
+        if let Some(depth_format) = self.format_info.depth_format
+        {
+            let image = depth_to_image(&mip_data, width, height, depth_format, &self.options)?;
+            return Ok(MipLevel { level, frame, face, slice, width, height, image });
+        }
+
+        let image_vec = match self.format_info.bc_format
+        {
+            Some(bc_format) => {
+                let mut bc_data = mip_data;
+                if self.vtfx.is_xbox() || self.options.force_dxt_endian_fix
+                {
+                    correct_dxt_endianness(&bc_format, &mut bc_data)?;
+                }
+
+                let mut image_vec = decode_bc(bc_format, &bc_data, width, height);
+                if bc_format == texpresso::Format::Bc5 && self.options.reconstruct_z
+                {
+                    reconstruct_normal_z(&mut image_vec);
+                }
+
+                image_vec
+            },
+            None => match (self.format_info.packed_format, self.palette)
+            {
+                (Some(packed_format), _) => packed_format.unpack(&mip_data),
+                (None, Some(palette)) => palette.expand(&mip_data),
+                (None, None) => mip_data
+            }
+        };
+
+        let mut default_pixel = self.format_info.default_pixel;
+        if self.vtfx.image_format == ImageFormat::IMAGE_FORMAT_UV88
+        {
+            default_pixel[2] = self.options.uv_blue;
+        }
+
+        let image = pixels_to_image(&image_vec, width, height, self.format_info, default_pixel, &self.options)?;
+        Ok(MipLevel { level, frame, face, slice, width, height, image })
+    }
+
+    ///Get the palette indices of a single volume slice of a face of a frame of a mip level of a P8 image
+    pub fn get_mip_indices(&self, level: usize, frame: usize, face: usize, slice: usize) -> Result<Vec<u8>, VtfxError>
+    {
+        if !self.format_info.paletted
+        {
+            return Err(VtfxError::NotPaletted(self.vtfx.image_format));
+        }
+
+        self.get_mip_slice_data(level, frame, face, slice)
+    }
+
+    ///Get the raw (not decoded) data of a single volume slice of a face of a frame of a mip level, untiled or deswizzled if needed
+    pub fn get_mip_slice_data(&self, level: usize, frame: usize, face: usize, slice: usize) -> Result<Vec<u8>, VtfxError>
+    {
+        if level >= self.mip_count
+        {
+            return Err(VtfxError::MipOutOfRange { level, mip_count: self.mip_count });
+        }
+
+        if frame >= self.frame_count
+        {
+            return Err(VtfxError::FrameOutOfRange { frame, frame_count: self.frame_count });
+        }
+
+        if face >= self.face_count
+        {
+            return Err(VtfxError::FaceOutOfRange { face, face_count: self.face_count });
+        }
+
+        let slice_count = self.slice_count(level);
+        if slice >= slice_count
+        {
+            return Err(VtfxError::SliceOutOfRange { slice, slice_count });
+        }
+
+        let (width, height) = self.vtfx.get_mip_size(level);
+        let surface_level = self.get_surface_level(level);
+        let mip_size = self.get_stored_slice_size(surface_level);
+        let mip_start = self.get_mip_offset(level, frame, face, slice);
+        let mip_data = self.data.get(mip_start..mip_start + mip_size)
+            .ok_or(VtfxError::SizeMismatch { expected: mip_start + mip_size, actual: self.data.len() })?;
+
+        let mut mip_data = mip_data.to_vec();
+        if self.untile
+        {
+            let (block_width, block_height, block_size) = get_block_layout(width, height, self.format_info);
+            let (surface_width, surface_height) = self.vtfx.get_mip_size(surface_level);
+            let (surface_block_width, _, _) = get_block_layout(surface_width, surface_height, self.format_info);
+            let block_dimension = match self.format_info.bc_format
+            {
+                Some(_) => 4,
+                None => 1
+            };
+            //Every mip in the mip tail (including the first) is positioned within the surface
+            let (x, y) = match self.mip_tail_start
+            {
+                Some(tail_start) if level >= tail_start => get_packed_mip_offset(self.vtfx.width as usize, self.vtfx.height as usize, level, block_dimension),
+                _ => (0, 0)
+            };
+            mip_data = untile_x360_region(&mip_data, surface_block_width, x, y, block_width, block_height, block_size);
+        }
+
+        if self.deswizzle
+        {
+            let pixel_size = self.format_info.get_pixel_size();
+            mip_data = deswizzle_ps3(&mip_data, width, height, pixel_size);
+        }
+
+        Ok(mip_data)
+    }
+
+    ///Decode every frame of a mip level (first face only)
+    pub fn get_frames(&self, level: usize) -> Result<Vec<MipLevel>, VtfxError>
+    {
+        (0..self.frame_count).map(|frame| self.get_mip_frame(level, frame)).collect()
+    }
+
+    ///Decode every face of a frame of a mip level
+    pub fn get_faces(&self, level: usize, frame: usize) -> Result<Vec<MipLevel>, VtfxError>
+    {
+        (0..self.face_count).map(|face| self.get_mip_face(level, frame, face)).collect()
+    }
+
+    ///Decode every volume slice of a frame of a mip level (first face only)
+    pub fn get_slices(&self, level: usize, frame: usize) -> Result<Vec<MipLevel>, VtfxError>
+    {
+        (0..self.slice_count(level)).map(|slice| self.get_mip_slice(level, frame, 0, slice)).collect()
+    }
+}
+
+impl Iterator for MipIterator<'_>
+{
+    type Item = Result<MipLevel, VtfxError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        if self.level >= self.mip_count
+        {
+            return None;
+        }
+
+        let mip = self.get_mip(self.level);
+        self.level += 1;
+        Some(mip)
+    }
+}
+
+///Extract low res image resource and return it as DynamicImage
+pub fn low_res_resource_to_image(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, vtfx: &VTFXHEADER, options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    let format_info = LOW_RES_IMAGE_FORMAT.try_get_format_info()?;
+    let bc_format = format_info.bc_format.ok_or(VtfxError::NotBlockCompressed(LOW_RES_IMAGE_FORMAT))?;
+    let (width, height) = vtfx.get_low_res_size();
+    let resource_buffer = low_res_resource_to_data(buffer, resource_entry_info, vtfx, options)?;
+
+    let image_vec = decode_bc(bc_format, &resource_buffer, width, height);
+    pixels_to_image(&image_vec, width, height, format_info, format_info.default_pixel, options)
+}
+
+///Read low res image resource as little endian dxt data (endianness is fixed for xbox 360 files)
+pub fn low_res_resource_to_data(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, vtfx: &VTFXHEADER, options: &DecodeOptions) -> Result<Vec<u8>, VtfxError>
+{
+    let res_start = resource_entry_info.resData as usize;
+    let bc_format = LOW_RES_IMAGE_FORMAT.try_get_format_info()?.bc_format.ok_or(VtfxError::NotBlockCompressed(LOW_RES_IMAGE_FORMAT))?;
+
+    let (width, height) = vtfx.get_low_res_size();
+    let bc_size = bc_format.compressed_size(width, height);
+    let mut resource_buffer = read_resource_data(buffer, res_start, bc_size)?;
+    if resource_buffer.len() < bc_size
+    {
+        return Err(VtfxError::SizeMismatch { expected: bc_size, actual: resource_buffer.len() });
+    }
+
+    //Resource size is not stored, only use the data for the low res image
+    resource_buffer.truncate(bc_size);
+
+    if vtfx.is_xbox() || options.force_dxt_endian_fix
+    {
+        correct_dxt_endianness(&bc_format, &mut resource_buffer)?;
+    }
+
+    Ok(resource_buffer)
+}
+
+///Parse a vtfx file from bytes and decode its low res image resource
+pub fn decode_low_res_image(buffer: &[u8], options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    let vtfx = VTFXHEADER::from(buffer)?;
+    let resource = vtfx.get_resource_entry_infos(buffer)?
+        .into_iter()
+        .find(|resource| resource.get_type() == ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE)
+        .ok_or(VtfxError::NoLowResImageResource)?;
+
+    low_res_resource_to_image(buffer, &resource, &vtfx, options)
+}
+
+///Copy resource data starting at res_start, decompressing it if it is lzma compressed
+fn read_resource_data(buffer: &[u8], res_start: usize, expected_size: usize) -> Result<Vec<u8>, VtfxError>
+{
+    if res_start >= buffer.len()
+    {
+        return Err(VtfxError::ResourceOutOfBounds { resource: 0, offset: res_start, file_size: buffer.len() });
+    }
+
+    //Copy input buffer
+    let mut resource_buffer = Vec::from(&buffer[res_start..buffer.len()]);
+
+    //Is this resource lzma compressed?
+    if is_lzma_compressed(&resource_buffer)
+    {
+        //Decompress and replace resource buffer
+        resource_buffer = decompress_lzma(&mut resource_buffer, expected_size)
+            .map_err(|error| match error {
+                VtfxError::Lzma { expected_size, message, .. } => VtfxError::Lzma { offset: res_start, expected_size, message },
+                error => error
+            })?;
+    }
+    else if resource_buffer.len() < expected_size
+    {
+        return Err(VtfxError::SizeMismatch { expected: expected_size, actual: resource_buffer.len() });
+    }
+
+    Ok(resource_buffer)
+}
+
+///Get width and height in blocks and the size of a block in bytes. Dxt blocks are 4x4 pixels, otherwise a block is 1 pixel
+pub(crate) fn get_block_layout(width: usize, height: usize, format_info: &image_format_info) -> (usize, usize, usize)
+{
+    match format_info.bc_format
+    {
+        Some(bc_format) => (width.div_ceil(4), height.div_ceil(4), bc_format.block_size()),
+        None => (width, height, format_info.get_pixel_size())
+    }
+}
+
+///Calculate blue (Z) of 4 channel normal map pixel data from red (X) and green (Y)
+fn reconstruct_normal_z(image_vec: &mut [u8])
+{
+    for pixel in image_vec.chunks_exact_mut(4)
+    {
+        let x = (pixel[0] as f32 / 127.5) - 1.0;
+        let y = (pixel[1] as f32 / 127.5) - 1.0;
+        let z = (1.0 - (x * x) - (y * y)).max(0.0).sqrt();
+        pixel[2] = ((z + 1.0) * 127.5).round() as u8;
+    }
+}
+
+///Decompress dxt data into 4 channel pixel data. Data must already be little endian
+pub(crate) fn decode_bc(bc_format: texpresso::Format, data: &[u8], width: usize, height: usize) -> Vec<u8>
+{
+    //Allocate space for 4 channels. Height is padded to whole blocks as texpresso writes full block rows
+    let block_height = height.next_multiple_of(4);
+    let mut image_vec = vec![0; width * block_height * 4];
+    bc_format.decompress(data, width, block_height, &mut image_vec);
+    image_vec.truncate(width * height * 4);
+    image_vec
+}
+
+///Take decoded pixel data and put into image, reordering channels using the format info.
+///Output channels that are not read from the source use default_pixel.
+///Formats with more than 8 bits per channel are output as 16 bit RGBA unless 8 bit export is enabled.
+fn pixels_to_image(image_vec: &[u8], width: usize, height: usize, format_info: &image_format_info, default_pixel: [u8; 4], options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    if format_info.float
+    {
+        return pixels_to_float_image(image_vec, width, height, format_info, default_pixel, options);
+    }
+
+    let width_u32 = width as u32;
+    let depth_u32 = format_info.depth as u32;
+    //Decoded dxt, unpacked and palette expanded data is always 4 channels
+    let source_channels = match format_info.bc_format.is_some() || format_info.packed_format.is_some() || format_info.paletted
+    {
+        true => 4,
+        false => format_info.channels as u32
+    };
+
+    //Alpha only formats (A8) have no colour, so their alpha is exported as grayscale unless alpha is exported
+    let alpha_as_grayscale = !options.export_alpha && format_info.channel_order.get(0..3).is_some_and(|channels| channels.iter().all(|channel| *channel == CHANNEL_NONE));
+
+    //Channels are kept as 16 bit until the output image is created
+    let mut output_pixels: Vec<u16> = Vec::with_capacity(width * height * 4);
+    for y in 0..height as u32
+    {
+        for x in 0..width_u32
+        {
+            let mut pixel: [u16; 4] = default_pixel.map(|channel| channel as u16 * 257);
+            //Index of pixel data to read from decoded output
+            let pixel_index = (x + y * width_u32) * depth_u32 * source_channels;
+            for (channel, channel_offset) in format_info.channel_order.iter().enumerate()
+            {
+                if *channel_offset == CHANNEL_NONE
+                {
+                    continue;
+                }
+
+                //Using format data, construct index and copy source image pixel colour data
+                //Add channel offset to pixel index.
+                let from_index: usize = (pixel_index + (*channel_offset as u32 * depth_u32)) as usize;
+
+                if from_index + (depth_u32 as usize) <= image_vec.len()
+                {
+                    pixel[channel] = get_pixel_as_u16(image_vec, from_index, &format_info.depth)?;
+                    if format_info.signed_channels[channel] && !options.raw_signed
+                    {
+                        //Flipping the sign bit of each byte offsets by 128 (0 becomes 128)
+                        pixel[channel] ^= 0x8080;
+                    }
+                }
+            }
+
+            if alpha_as_grayscale
+            {
+                pixel = [pixel[3], pixel[3], pixel[3], u16::MAX];
+            }
+
+            //Override alpha if not explicitly enabled
+            if !options.export_alpha
+            {
+                pixel[3] = u16::MAX;
+            }
+            else if options.onebit_alpha
+            {
+                pixel[3] = match u16_to_u8(pixel[3]) > 1 {
+                    true => 0,
+                    false => u16::MAX
+                };
+            }
+
+            //Source converts the blue key to transparent black
+            if format_info.bluescreen && !options.keep_bluescreen && pixel[0..3] == [0, 0, u16::MAX]
+            {
+                pixel = [0; 4];
+            }
+
+            output_pixels.extend_from_slice(&pixel);
+        }
+    }
+
+    let output_image = if format_info.depth > 1 && !options.eight_bit
+    {
+        let image = ImageBuffer::from_raw(width_u32, height as u32, output_pixels).ok_or(VtfxError::SizeMismatch { expected: width * height * 4, actual: 0 })?;
+        DynamicImage::ImageRgba16(image)
+    }
+    else
+    {
+        let pixels_u8: Vec<u8> = output_pixels.into_iter().map(u16_to_u8).collect();
+        let image = RgbaImage::from_raw(width_u32, height as u32, pixels_u8).ok_or(VtfxError::SizeMismatch { expected: width * height * 4, actual: 0 })?;
+        DynamicImage::ImageRgba8(image)
+    };
+
+    if alpha_as_grayscale
+    {
+        return Ok(to_grayscale(&output_image, false));
+    }
+
+    if format_info.grayscale && !options.no_grayscale
+    {
+        let has_alpha = format_info.channel_order.len() > 3;
+        return Ok(to_grayscale(&output_image, options.export_alpha && has_alpha));
+    }
+
+    Ok(output_image)
+}
+
+///Take float pixel data and put into a 32 bit float RGBA image, reordering channels using the format info
+fn pixels_to_float_image(image_vec: &[u8], width: usize, height: usize, format_info: &image_format_info, default_pixel: [u8; 4], options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    let depth = format_info.depth as usize;
+    let pixel_size = depth * format_info.channels as usize;
+
+    let mut output_pixels: Vec<f32> = Vec::with_capacity(width * height * 4);
+    for pixel_index in (0..width * height).map(|pixel| pixel * pixel_size)
+    {
+        let mut pixel: [f32; 4] = default_pixel.map(|channel| channel as f32 / 255.0);
+        for (channel, channel_offset) in format_info.channel_order.iter().enumerate()
+        {
+            if *channel_offset == CHANNEL_NONE
+            {
+                continue;
+            }
+
+            let from_index = pixel_index + (*channel_offset as usize * depth);
+            if from_index + depth <= image_vec.len()
+            {
+                pixel[channel] = get_pixel_as_f32(image_vec, from_index, &format_info.depth)?;
+            }
+        }
+
+        if !options.export_alpha
+        {
+            pixel[3] = 1.0;
+        }
+        else if options.onebit_alpha
+        {
+            //Same as 8 bit alpha, anything above 1 / 255 is transparent
+            pixel[3] = match (pixel[3] * 255.0).round() > 1.0 {
+                true => 0.0,
+                false => 1.0
+            };
+        }
+
+        output_pixels.extend_from_slice(&pixel);
+    }
+
+    let image = Rgba32FImage::from_raw(width as u32, height as u32, output_pixels).ok_or(VtfxError::SizeMismatch { expected: width * height * 4, actual: 0 })?;
+    Ok(DynamicImage::ImageRgba32F(image))
+}
+
+///Decode depth pixels to a grayscale image. Integer depths are 16 bit (8 bit if eight_bit is set), float depths are 32 bit float RGB
+fn depth_to_image(data: &[u8], width: usize, height: usize, depth_format: DepthFormat, options: &DecodeOptions) -> Result<DynamicImage, VtfxError>
+{
+    let depths: Vec<f32> = data.chunks_exact(depth_format.get_pixel_size()).take(width * height).map(|pixel| depth_format.read(pixel)).collect();
+    let size_error = VtfxError::SizeMismatch { expected: width * height * depth_format.get_pixel_size(), actual: data.len() };
+    let (width, height) = (width as u32, height as u32);
+
+    let image = if depth_format.is_float()
+    {
+        let pixels = depths.iter().flat_map(|depth| [*depth, *depth, *depth, 1.0]).collect();
+        DynamicImage::ImageRgba32F(Rgba32FImage::from_raw(width, height, pixels).ok_or(size_error)?)
+    }
+    else if options.eight_bit
+    {
+        let pixels = depths.iter().map(|depth| (depth * u8::MAX as f32).round() as u8).collect();
+        DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, pixels).ok_or(size_error)?)
+    }
+    else
+    {
+        let pixels = depths.iter().map(|depth| (depth * u16::MAX as f32).round() as u16).collect();
+        DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, pixels).ok_or(size_error)?)
+    };
+
+    Ok(image)
+}
+
+///Convert image with luminance in all RGB channels to a grayscale (L8 or LA8) image
+fn to_grayscale(image: &DynamicImage, alpha: bool) -> DynamicImage
+{
+    let rgba = image.to_rgba8();
+    match alpha
+    {
+        true => DynamicImage::ImageLumaA8(GrayAlphaImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            let pixel = rgba.get_pixel(x, y);
+            LumaA([pixel[0], pixel[3]])
+        })),
+        false => DynamicImage::ImageLuma8(GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| Luma([rgba.get_pixel(x, y)[0]])))
+    }
+}
+
+///Decompress resource that is compressed via lzma. Creates new buffer with new header.
+pub fn decompress_lzma(resource_buffer: &mut Vec<u8>, expected_compressed_size: usize) -> Result<Vec<u8>, VtfxError>
+{
+    //Valve lzma header is magic, actual size, compressed size and 5 bytes of properties
+    if resource_buffer.len() < 17
+    {
+        return Err(VtfxError::Lzma { offset: 0, expected_size: expected_compressed_size, message: format!("lzma header is truncated ({} bytes)", resource_buffer.len()) });
+    }
+
+    //Read data from valves lzma header
+    let actual_size: u64 = read_u32_le(resource_buffer, 4).into();
+    let mut decomp: Vec<u8> = Vec::with_capacity(actual_size as usize);
+    let mut dictionary_size: u32 = 0;
+    let props_data = &resource_buffer[12..16];
+    for i in 0..3
+    {
+        dictionary_size += (props_data[1 + i] as u32) << (i * 8);
+    }
+    if dictionary_size == 0
+    {
+        dictionary_size = 1;
+    }
+
+    //Reconstruct new header + data to decompress
+    let mut new_header_resource_buffer: Vec<u8> = Vec::with_capacity(resource_buffer.len());
+    new_header_resource_buffer.push(resource_buffer[12]);
+
+    new_header_resource_buffer.extend_from_slice(&dictionary_size.to_le_bytes());
+    new_header_resource_buffer.extend_from_slice(&actual_size.to_le_bytes());
+    new_header_resource_buffer.extend_from_slice(&resource_buffer[17..]);
+    *resource_buffer = new_header_resource_buffer;
+
+    lzma_rs::lzma_decompress(&mut &resource_buffer[..], &mut decomp)
+        .map_err(|error| VtfxError::Lzma { offset: 0, expected_size: expected_compressed_size, message: format!("{:?}", error) })?;
+    Ok(decomp)
+}
+
+///Get channel as u16. 8 bit channels are scaled up and 32 bit channels are scaled down
+fn get_pixel_as_u16(in_buffer: &[u8], index: usize, depth: &u16) -> Result<u16, VtfxError>
+{
+    let colour = match depth
+    {
+        1 => in_buffer[index] as u16 * 257,
+        2 => u16::from_be_bytes([in_buffer[index], in_buffer[index + 1]]),
+        4 => (u32::from_be_bytes([in_buffer[index], in_buffer[index + 1], in_buffer[index + 2], in_buffer[index + 3]]) >> 16) as u16,
+        _ => return Err(VtfxError::UnsupportedDepth(*depth))
+    };
+
+    Ok(colour)
+}
+
+///Get big endian float channel as f32. 2 byte channels are half floats
+fn get_pixel_as_f32(in_buffer: &[u8], index: usize, depth: &u16) -> Result<f32, VtfxError>
+{
+    let colour = match depth
+    {
+        2 => f16::from_be_bytes([in_buffer[index], in_buffer[index + 1]]).to_f32(),
+        4 => f32::from_be_bytes([in_buffer[index], in_buffer[index + 1], in_buffer[index + 2], in_buffer[index + 3]]),
+        _ => return Err(VtfxError::UnsupportedDepth(*depth))
+    };
+
+    Ok(colour)
+}
+
+///Scale 16 bit channel to 8 bits, rounding to the nearest value
+fn u16_to_u8(colour: u16) -> u8
+{
+    ((colour as u32 + 128) / 257) as u8
+}
+
+///Read little endian u32 at offset
+fn read_u32_le(buffer: &[u8], offset: usize) -> u32
+{
+    u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]])
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_util::{header, mipped_header};
+    use crate::vtfx::{VTF_PS3_MAJOR_VERSION, VTF_X360_MAJOR_VERSION};
+
+    ///Compress data and wrap it in a valve lzma header (magic, actual size, compressed size, 5 bytes of properties)
+    fn valve_lzma(data: &[u8]) -> Vec<u8>
+    {
+        let mut lzma = Vec::new();
+        lzma_rs::lzma_compress(&mut &data[..], &mut lzma).unwrap();
+
+        //Standard lzma header is 5 bytes of properties and a u64 actual size
+        let compressed = &lzma[13..];
+        let mut buffer = LZMA_MAGIC.to_vec();
+        buffer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&lzma[0..5]);
+        buffer.extend_from_slice(compressed);
+        buffer
+    }
+
+    #[test]
+    fn lzma_magic_is_detected()
+    {
+        assert!(is_lzma_compressed(b"LZMA\0\0\0\0"));
+        assert!(!is_lzma_compressed(b"LZM"));
+        assert!(!is_lzma_compressed(b"VTFX\0\0\0\0"));
+    }
+
+    #[test]
+    fn lzma_resource_is_decompressed()
+    {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let mut buffer = valve_lzma(&data);
+        let decompressed = decompress_lzma(&mut buffer, data.len()).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn truncated_lzma_header_is_rejected()
+    {
+        let mut buffer = valve_lzma(&[1, 2, 3, 4]);
+        buffer.truncate(16);
+        let error = decompress_lzma(&mut buffer, 4).unwrap_err();
+        assert!(matches!(error, VtfxError::Lzma { expected_size: 4, .. }), "{:?}", error);
+    }
+
+    #[test]
+    fn corrupt_lzma_stream_is_rejected()
+    {
+        let data = [7u8; 64];
+        let mut buffer = valve_lzma(&data);
+        //Invalid properties byte (must be below 9 * 5 * 5)
+        buffer[12] = 0xFF;
+        let error = decompress_lzma(&mut buffer, data.len()).unwrap_err();
+        assert!(matches!(error, VtfxError::Lzma { .. }), "{:?}", error);
+    }
+
+    #[test]
+    fn lzma_error_reports_resource_offset()
+    {
+        let mut buffer = vec![0u8; 32];
+        let mut lzma = valve_lzma(&[1, 2, 3, 4]);
+        lzma.truncate(10);
+        buffer.extend_from_slice(&lzma);
+
+        let error = read_resource_data(&buffer, 32, 4).unwrap_err();
+        assert!(matches!(error, VtfxError::Lzma { offset: 32, expected_size: 4, .. }), "{:?}", error);
+    }
+
+    #[test]
+    fn low_res_data_is_truncated_and_byte_swapped()
+    {
+        //32x8 image has a 16x4 low res image, 4 dxt1 blocks. Followed by data that is not part of the resource
+        let mut buffer: Vec<u8> = (0..32).collect();
+        buffer.extend_from_slice(&[0xAA; 8]);
+        let resource = ResourceEntryInfo { chTypeBytes: [0x01, 0, 0, 0], resData: 0 };
+
+        let vtfx = header(VTF_X360_MAJOR_VERSION, 32, 8, ImageFormat::IMAGE_FORMAT_DXT1);
+        let data = low_res_resource_to_data(&buffer, &resource, &vtfx, &DecodeOptions::default()).unwrap();
+        let swapped: Vec<u8> = (0..32u8).map(|i| i ^ 1).collect();
+        assert_eq!(data, swapped);
+
+        let vtfx = header(VTF_PS3_MAJOR_VERSION, 32, 8, ImageFormat::IMAGE_FORMAT_DXT1);
+        let data = low_res_resource_to_data(&buffer, &resource, &vtfx, &DecodeOptions::default()).unwrap();
+        assert_eq!(data, buffer[..32]);
+    }
+
+    #[test]
+    fn short_low_res_data_is_rejected()
+    {
+        let buffer = [0u8; 24];
+        let resource = ResourceEntryInfo { chTypeBytes: [0x01, 0, 0, 0], resData: 0 };
+        let vtfx = header(VTF_PS3_MAJOR_VERSION, 32, 8, ImageFormat::IMAGE_FORMAT_DXT1);
+        let result = low_res_resource_to_data(&buffer, &resource, &vtfx, &DecodeOptions::default());
+        assert!(matches!(result, Err(VtfxError::SizeMismatch { expected: 32, actual: 24 })), "{:?}", result);
+    }
+
+    #[test]
+    fn low_res_image_is_decoded()
+    {
+        //Two dxt1 blocks with both colours pure red, 8x4 is small enough to be its own low res size
+        let blocks = [0x00, 0xF8, 0x00, 0xF8, 0, 0, 0, 0].repeat(2);
+        let resource = ResourceEntryInfo { chTypeBytes: [0x01, 0, 0, 0], resData: 0 };
+        let vtfx = header(VTF_PS3_MAJOR_VERSION, 8, 4, ImageFormat::IMAGE_FORMAT_DXT1);
+        let image = low_res_resource_to_image(&blocks, &resource, &vtfx, &DecodeOptions::default()).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+        assert!(image.to_rgba8().pixels().all(|pixel| pixel.0 == [255, 0, 0, 255]));
+    }
+
+    const IMAGE_RESOURCE: ResourceEntryInfo = ResourceEntryInfo { chTypeBytes: [0x30, 0, 0, 0], resData: 0 };
+
+    fn mip_offsets(mips: &MipIterator) -> Vec<usize>
+    {
+        (0..mips.mip_count()).map(|level| mips.get_mip_offset(level, 0, 0, 0)).collect()
+    }
+
+    #[test]
+    fn ps3_uncompressed_mips_are_largest_first()
+    {
+        //8x4, 4x2, 2x1 and 1x1 four byte pixels
+        let vtfx = mipped_header(VTF_PS3_MAJOR_VERSION, 8, 4, ImageFormat::IMAGE_FORMAT_RGBA8888);
+        let data: Vec<u8> = [(128, 10), (32, 20), (8, 30), (4, 40)].iter().flat_map(|&(size, value)| vec![value; size]).collect();
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+
+        assert_eq!(mips.mip_count(), 4);
+        assert_eq!(mip_offsets(&mips), [0, 128, 160, 168]);
+        for (level, mip) in mips.enumerate()
+        {
+            let mip = mip.unwrap();
+            assert_eq!((mip.width, mip.height), (8 >> level, (4 >> level).max(1)));
+            let value = 10 * (level as u8 + 1);
+            assert!(mip.image.to_rgba8().pixels().all(|pixel| pixel.0 == [value, value, value, 255]), "mip {}", level);
+        }
+    }
+
+    #[test]
+    fn dxt_mips_are_smallest_first()
+    {
+        //8x8 has 4 dxt1 blocks, every smaller mip is 1 block
+        let vtfx = mipped_header(VTF_PS3_MAJOR_VERSION, 8, 8, ImageFormat::IMAGE_FORMAT_DXT1);
+        let data = vec![0; 56];
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        assert_eq!(mip_offsets(&mips), [24, 16, 8, 0]);
+    }
+
+    #[test]
+    fn xbox_mips_are_smallest_first()
+    {
+        //4x4, 2x2 and 1x1 four byte pixels
+        let vtfx = mipped_header(VTF_X360_MAJOR_VERSION, 4, 4, ImageFormat::IMAGE_FORMAT_LINEAR_RGBA8888);
+        let data = vec![0; 84];
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        assert_eq!(mip_offsets(&mips), [20, 4, 0]);
+    }
+
+    #[test]
+    fn missing_mip_data_is_rejected()
+    {
+        let vtfx = mipped_header(VTF_PS3_MAJOR_VERSION, 8, 4, ImageFormat::IMAGE_FORMAT_RGBA8888);
+        let mips = resource_to_mips(&[0; 165], &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        assert!(mips.get_mip(1).is_ok());
+        assert!(matches!(mips.get_mip(3), Err(VtfxError::SizeMismatch { expected: 172, actual: 165 })));
+        assert!(matches!(mips.get_mip(4), Err(VtfxError::MipOutOfRange { level: 4, mip_count: 4 })));
+    }
+
+    #[test]
+    fn frames_are_stored_within_each_mip()
+    {
+        //2 frames of 4x2, 2x1 and 1x1 four byte pixels, each mip has every frame
+        let mut vtfx = mipped_header(VTF_PS3_MAJOR_VERSION, 4, 2, ImageFormat::IMAGE_FORMAT_RGBA8888);
+        vtfx.num_frames = 2;
+        let data: Vec<u8> = [(32, 1), (32, 2), (8, 3), (8, 4), (4, 5), (4, 6)].iter().flat_map(|&(size, value)| vec![value; size]).collect();
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+
+        assert_eq!(mips.frame_count(), 2);
+        let offsets: Vec<(usize, usize)> = (0..3).map(|level| (mips.get_mip_offset(level, 0, 0, 0), mips.get_mip_offset(level, 1, 0, 0))).collect();
+        assert_eq!(offsets, [(0, 32), (64, 72), (80, 84)]);
+
+        for level in 0..3
+        {
+            let frames = mips.get_frames(level).unwrap();
+            let values: Vec<u8> = frames.iter().map(|frame| frame.image.to_rgba8().get_pixel(0, 0).0[0]).collect();
+            assert_eq!(values, [level as u8 * 2 + 1, level as u8 * 2 + 2], "mip {}", level);
+        }
+
+        assert!(matches!(mips.get_mip_frame(0, 2), Err(VtfxError::FrameOutOfRange { frame: 2, frame_count: 2 })));
+    }
+
+    #[test]
+    fn xbox_frames_are_stored_within_each_mip()
+    {
+        //Mips are smallest first but frames are still in order
+        let mut vtfx = mipped_header(VTF_X360_MAJOR_VERSION, 2, 2, ImageFormat::IMAGE_FORMAT_LINEAR_RGBA8888);
+        vtfx.num_frames = 3;
+        let mips = resource_to_mips(&[0; 60], &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        let offsets: Vec<usize> = (0..3).map(|frame| mips.get_mip_offset(1, frame, 0, 0)).collect();
+        assert_eq!(offsets, [0, 4, 8]);
+        let offsets: Vec<usize> = (0..3).map(|frame| mips.get_mip_offset(0, frame, 0, 0)).collect();
+        assert_eq!(offsets, [12, 28, 44]);
+    }
+
+    #[test]
+    fn volume_slices_are_stored_within_each_mip()
+    {
+        //2x2x4, 1x1x2 and 1x1x1 four byte pixels, the slice count halves with each mip
+        let mut vtfx = header(VTF_PS3_MAJOR_VERSION, 2, 2, ImageFormat::IMAGE_FORMAT_RGBA8888);
+        vtfx.depth = 4;
+        vtfx.mip_count = crate::image_format::GetNumMipMapLevels(2, 2, 4);
+        let data: Vec<u8> = [(16, 1), (16, 2), (16, 3), (16, 4), (4, 5), (4, 6), (4, 7)].iter().flat_map(|&(size, value)| vec![value; size]).collect();
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+
+        assert_eq!(mips.mip_count(), 3);
+        assert_eq!((0..3).map(|level| mips.slice_count(level)).collect::<Vec<usize>>(), [4, 2, 1]);
+        let offsets: Vec<usize> = (0..4).map(|slice| mips.get_mip_offset(0, 0, 0, slice)).collect();
+        assert_eq!(offsets, [0, 16, 32, 48]);
+        assert_eq!((mips.get_mip_offset(1, 0, 0, 1), mips.get_mip_offset(2, 0, 0, 0)), (68, 72));
+
+        let values: Vec<u8> = (0..3).flat_map(|level| mips.get_slices(level, 0).unwrap())
+            .map(|slice| slice.image.to_rgba8().get_pixel(0, 0).0[0])
+            .collect();
+        assert_eq!(values, [1, 2, 3, 4, 5, 6, 7]);
+        assert!(matches!(mips.get_mip_slice(1, 0, 0, 2), Err(VtfxError::SliceOutOfRange { slice: 2, slice_count: 2 })));
+    }
+
+    #[test]
+    fn cubemap_face_count_ignores_trailing_data()
+    {
+        //6 faces of 2x2 followed by enough data for a 7th (spheremap) face, which vtfx never has
+        let mut vtfx = header(VTF_PS3_MAJOR_VERSION, 2, 2, ImageFormat::IMAGE_FORMAT_LINEAR_I8);
+        vtfx.flags = 0x4000;
+        vtfx.mip_count = 1;
+        let data: Vec<u8> = (0..7).flat_map(|face| [face; 4]).collect();
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+
+        assert_eq!(mips.face_count(), 6);
+        let faces = mips.get_faces(0, 0).unwrap();
+        let values: Vec<u8> = faces.iter().map(|face| face.image.to_luma8().get_pixel(1, 1).0[0]).collect();
+        assert_eq!(values, [0, 1, 2, 3, 4, 5]);
+        assert!(matches!(mips.get_mip_face(0, 0, 6), Err(VtfxError::FaceOutOfRange { face: 6, face_count: 6 })));
+    }
+
+    #[test]
+    fn xbox_mips_are_tiled_with_a_mip_tail()
+    {
+        //32x32 dxt1: mip 0 is 8x8 blocks, mips 1 to 5 (16x16 and smaller) are packed into one surface stored first
+        let vtfx = mipped_header(VTF_X360_MAJOR_VERSION, 32, 32, ImageFormat::IMAGE_FORMAT_DXT1);
+        let tiled_size = crate::untile::get_tiled_size(8, 8, 8);
+        let mut data = vec![0u8; tiled_size * 2];
+
+        //Pure colour blocks (u16 swapped), a different colour for each mip at its position in its surface
+        let colours: [u16; 6] = [0xF800, 0x07E0, 0x001F, 0xFFE0, 0xF81F, 0x07FF];
+        let surfaces = [(1, 0, 0, 8), (0, 4, 0, 4), (0, 2, 0, 2), (0, 1, 0, 1), (0, 0, 2, 1), (0, 0, 1, 1)];
+        for (level, &(surface, x, y, blocks)) in surfaces.iter().enumerate()
+        {
+            let [low, high] = colours[level].to_le_bytes();
+            let block = [high, low, high, low, 0, 0, 0, 0].repeat(blocks * blocks);
+            let start = surface * tiled_size;
+            crate::untile::tile_x360_region(&block, &mut data[start..start + tiled_size], 8, x, y, blocks, blocks, 8);
+        }
+
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        assert!(mips.is_tiled());
+        assert_eq!(mips.get_total_size(), tiled_size * 2);
+        assert_eq!(mip_offsets(&mips), [tiled_size, 0, 0, 0, 0, 0]);
+
+        let expected: [[u8; 4]; 6] = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [255, 255, 0, 255], [255, 0, 255, 255], [0, 255, 255, 255]];
+        for (level, mip) in mips.enumerate()
+        {
+            let image = mip.unwrap().image.to_rgba8();
+            assert!(image.pixels().all(|pixel| pixel.0 == expected[level]), "mip {}", level);
+        }
+    }
+
+    #[test]
+    fn xbox_untile_can_be_disabled()
+    {
+        let vtfx = mipped_header(VTF_X360_MAJOR_VERSION, 4, 4, ImageFormat::IMAGE_FORMAT_BGRA8888);
+        let options = DecodeOptions { no_untile: true, ..Default::default() };
+        let mips = resource_to_mips(&[0; 84], &IMAGE_RESOURCE, &vtfx, &options).unwrap();
+        assert!(!mips.is_tiled());
+        assert_eq!(mips.get_total_size(), 84);
+    }
+
+    #[test]
+    fn untiling_depends_on_format()
+    {
+        let options = DecodeOptions::default();
+        let tiled = [
+            (ImageFormat::IMAGE_FORMAT_BGRA8888, true),
+            (ImageFormat::IMAGE_FORMAT_DXT5, true),
+            (ImageFormat::IMAGE_FORMAT_I8, true),
+            (ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888, false),
+            (ImageFormat::IMAGE_FORMAT_RGB888, false),
+        ];
+        for (image_format, expected) in tiled
+        {
+            let vtfx = header(VTF_X360_MAJOR_VERSION, 4, 4, image_format);
+            let mips = resource_to_mips(&[0; 4096], &IMAGE_RESOURCE, &vtfx, &options).unwrap();
+            assert_eq!(mips.is_tiled(), expected, "{:?}", image_format);
+
+            let vtfx = header(VTF_PS3_MAJOR_VERSION, 4, 4, image_format);
+            let mips = resource_to_mips(&[0; 4096], &IMAGE_RESOURCE, &vtfx, &options).unwrap();
+            assert!(!mips.is_tiled(), "ps3 {:?}", image_format);
+        }
+    }
+
+    #[test]
+    fn ps3_uncompressed_mips_are_swizzled()
+    {
+        //4x4 then 2x2 and 1x1, each pixel value is its swizzled position
+        let vtfx = mipped_header(VTF_PS3_MAJOR_VERSION, 4, 4, ImageFormat::IMAGE_FORMAT_I8);
+        let data: Vec<u8> = (0..16).chain(0..4).chain(0..1).collect();
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        assert!(mips.is_swizzled());
+
+        let mip = mips.get_mip(0).unwrap().image.to_luma8();
+        assert_eq!(mip.as_raw(), &[0, 1, 4, 5, 2, 3, 6, 7, 8, 9, 12, 13, 10, 11, 14, 15]);
+        let mip = mips.get_mip(1).unwrap().image.to_luma8();
+        assert_eq!(mip.as_raw(), &[0, 1, 2, 3]);
+
+        let options = DecodeOptions { no_deswizzle: true, ..Default::default() };
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &options).unwrap();
+        assert!(!mips.is_swizzled());
+        assert_eq!(mips.get_mip(0).unwrap().image.to_luma8().as_raw(), &data[..16]);
+    }
+
+    #[test]
+    fn deswizzling_depends_on_format_and_size()
+    {
+        let swizzled = [
+            (ImageFormat::IMAGE_FORMAT_BGRA8888, 4, true),
+            (ImageFormat::IMAGE_FORMAT_I8, 4, true),
+            (ImageFormat::IMAGE_FORMAT_BGRA8888, 6, false),
+            (ImageFormat::IMAGE_FORMAT_DXT1, 4, false),
+            (ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888, 4, false),
+        ];
+        for (image_format, width, expected) in swizzled
+        {
+            let vtfx = header(VTF_PS3_MAJOR_VERSION, width, 4, image_format);
+            let mips = resource_to_mips(&[0; 96], &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+            assert_eq!(mips.is_swizzled(), expected, "{:?} {}x4", image_format, width);
+
+            let vtfx = header(VTF_X360_MAJOR_VERSION, width, 4, image_format);
+            let mips = resource_to_mips(&[0; 4096], &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+            assert!(!mips.is_swizzled(), "xbox {:?} {}x4", image_format, width);
+        }
+    }
+
+    #[test]
+    fn a8_exports_alpha_as_grayscale()
+    {
+        let vtfx = header(VTF_PS3_MAJOR_VERSION, 4, 1, ImageFormat::IMAGE_FORMAT_A8);
+        let data = [0, 64, 128, 255];
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &DecodeOptions::default()).unwrap();
+        let image = mips.get_mip(0).unwrap().image;
+        assert_eq!(image.as_luma8().unwrap().as_raw(), &data);
+
+        let options = DecodeOptions { export_alpha: true, ..Default::default() };
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &options).unwrap();
+        let image = mips.get_mip(0).unwrap().image.to_rgba8();
+        let pixels: Vec<[u8; 4]> = image.pixels().map(|pixel| pixel.0).collect();
+        assert_eq!(pixels, [[0, 0, 0, 0], [0, 0, 0, 64], [0, 0, 0, 128], [0, 0, 0, 255]]);
+    }
+
+    #[test]
+    fn onebit_alpha_applies_to_float_images()
+    {
+        //2x1 RGBA16161616F, alpha of 0 and 0.5
+        let vtfx = header(VTF_PS3_MAJOR_VERSION, 2, 1, ImageFormat::IMAGE_FORMAT_RGBA16161616F);
+        let data: Vec<u8> = [1.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 0.5].iter().flat_map(|&value: &f32| f16::from_f32(value).to_be_bytes()).collect();
+        let options = DecodeOptions { export_alpha: true, onebit_alpha: true, ..Default::default() };
+        let mips = resource_to_mips(&data, &IMAGE_RESOURCE, &vtfx, &options).unwrap();
+        let image = mips.get_mip(0).unwrap().image;
+        let alpha: Vec<f32> = image.as_rgba32f().unwrap().pixels().map(|pixel| pixel.0[3]).collect();
+        assert_eq!(alpha, [1.0, 0.0]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::{error::Error, fmt};
+
+use crate::ImageFormat;
+
+///Errors that can occur while parsing or decoding a vtfx file
+#[derive(Debug)]
+pub enum VtfxError
+{
+    ///File does not start with "VTFX"
+    BadMagic { found: [u8; 4] },
+    ///File does not start with "VTF\0" (PC vtf)
+    BadVtfMagic { found: [u8; 4] },
+    ///Header version is not a known console vtfx version
+    UnsupportedVersion { major: i32, minor: i32 },
+    ///Image format id in the header does not map to a known ImageFormat
+    UnknownImageFormat { id: i32, offset: usize },
+    ///Buffer ended before the header could be fully read
+    TruncatedHeader { offset: usize, needed: usize, available: usize },
+    ///Resource data starts or ends outside of the file
+    ResourceOutOfBounds { resource: usize, offset: usize, file_size: usize },
+    ///Resource data chunk ended before it could be fully read
+    TruncatedResource { resource: usize, offset: usize, needed: usize },
+    ///Vtfx has no image resource to decode
+    NoImageResource,
+    ///Vtfx has no low res image resource to decode
+    NoLowResImageResource,
+    ///LZMA header was invalid or decompression failed
+    Lzma { offset: usize, expected_size: usize, message: String },
+    ///Data is not the size required to decode it
+    SizeMismatch { expected: usize, actual: usize },
+    ///Requested mip level does not exist
+    MipOutOfRange { level: usize, mip_count: usize },
+    ///Requested frame does not exist
+    FrameOutOfRange { frame: usize, frame_count: usize },
+    ///Requested cubemap face does not exist
+    FaceOutOfRange { face: usize, face_count: usize },
+    ///Requested volume slice does not exist
+    SliceOutOfRange { slice: usize, slice_count: usize },
+    ///Image format is known but cannot be decoded
+    UnsupportedFormat(ImageFormat),
+    ///Image format is not block compressed (DXT)
+    NotBlockCompressed(ImageFormat),
+    ///Block compressed data length is not a multiple of the block size
+    InvalidBlockLength { block_size: usize, length: usize },
+    ///Image format is not palettized (P8)
+    NotPaletted(ImageFormat),
+    ///P8 image has no palette and none was given
+    NoPalette,
+    ///Palette data is not 256 RGB or RGBA colours
+    InvalidPalette { length: usize },
+    ///Image format is console only and has no PC equivalent
+    NoPcFormat(ImageFormat),
+    ///PC vtf version cannot be read or written
+    UnsupportedVtfVersion { major: u32, minor: u32 },
+    ///Image has some but not all mip levels, vtfx only supports 1 or all mip levels
+    PartialMipChain { mip_count: usize, expected: usize },
+    ///Channel depth (in bytes) is not supported
+    UnsupportedDepth(u16),
+    ///Image could not be encoded
+    Image(image::ImageError),
+    ///Output could not be written
+    Io(std::io::Error),
+}
+
+impl fmt::Display for VtfxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self
+        {
+            VtfxError::BadMagic { found } => write!(f, "File is not VTFX file! (magic: {:?})", found),
+            VtfxError::BadVtfMagic { found } => write!(f, "File is not a PC VTF file! (magic: {:?})", found),
+            VtfxError::UnsupportedVersion { major, minor } => write!(f, "Unsupported vtfx version {}.{} ({:#x}.{})", major, minor, major, minor),
+            VtfxError::UnknownImageFormat { id, offset } => write!(f, "Unknown image format id {} at offset {}", id, offset),
+            VtfxError::TruncatedHeader { offset, needed, available } => write!(f, "Header is truncated, needed {} bytes at offset {} but file is {} bytes", needed, offset, available),
+            VtfxError::ResourceOutOfBounds { resource, offset, file_size } => write!(f, "Resource #{} at offset {} is outside of file (size {})", resource, offset, file_size),
+            VtfxError::TruncatedResource { resource, offset, needed } => write!(f, "Resource #{} is truncated, needed {} bytes at offset {}", resource, needed, offset),
+            VtfxError::NoImageResource => write!(f, "vtfx has no image resource"),
+            VtfxError::NoLowResImageResource => write!(f, "vtfx has no low res image resource"),
+            VtfxError::Lzma { offset, expected_size, message } => write!(f, "LZMA decompression of resource at offset {} failed (expected size {}): {}", offset, expected_size, message),
+            VtfxError::SizeMismatch { expected, actual } => write!(f, "resource size is {} but expected length is {}, resource cannot decoded", actual, expected),
+            VtfxError::MipOutOfRange { level, mip_count } => write!(f, "Mip level {} does not exist, vtfx has {} mip levels", level, mip_count),
+            VtfxError::FrameOutOfRange { frame, frame_count } => write!(f, "Frame {} does not exist, vtfx has {} frames", frame, frame_count),
+            VtfxError::FaceOutOfRange { face, face_count } => write!(f, "Face {} does not exist, vtfx has {} faces", face, face_count),
+            VtfxError::SliceOutOfRange { slice, slice_count } => write!(f, "Slice {} does not exist, mip has {} slices", slice, slice_count),
+            VtfxError::UnsupportedFormat(image_format) => write!(f, "Unsupported image format: {:?}.\nRequest for other formats to be supported on github.", image_format),
+            VtfxError::NotBlockCompressed(image_format) => write!(f, "Image format {:?} is not DXT", image_format),
+            VtfxError::InvalidBlockLength { block_size, length } => write!(f, "Length of dxt buffer should be multiple of {}. Length: {}", block_size, length),
+            VtfxError::NotPaletted(image_format) => write!(f, "Image format {:?} is not palettized", image_format),
+            VtfxError::NoPalette => write!(f, "P8 image has no palette, one must be given (e.g. with --palette)"),
+            VtfxError::InvalidPalette { length } => write!(f, "Palette should be 256 RGB (768 bytes) or RGBA (1024 bytes) colours. Length: {}", length),
+            VtfxError::NoPcFormat(image_format) => write!(f, "Image format {:?} has no PC equivalent", image_format),
+            VtfxError::UnsupportedVtfVersion { major, minor } => write!(f, "Unsupported vtf version {}.{}", major, minor),
+            VtfxError::PartialMipChain { mip_count, expected } => write!(f, "Image has {} mip levels but vtfx needs 1 or all {} mip levels", mip_count, expected),
+            VtfxError::UnsupportedDepth(depth) => write!(f, "Unexpected depth size '{}'", depth),
+            VtfxError::Image(error) => write!(f, "Image error: {}", error),
+            VtfxError::Io(error) => write!(f, "IO error: {}", error),
+        }
+    }
+}
+
+impl Error for VtfxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self
+        {
+            VtfxError::Image(error) => Some(error),
+            VtfxError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<image::ImageError> for VtfxError {
+    fn from(error: image::ImageError) -> Self {
+        VtfxError::Image(error)
+    }
+}
+
+impl From<png::EncodingError> for VtfxError {
+    fn from(error: png::EncodingError) -> Self {
+        VtfxError::Image(image::ImageError::Encoding(image::error::EncodingError::new(image::error::ImageFormatHint::Exact(image::ImageFormat::Png), error)))
+    }
+}
+
+impl From<std::io::Error> for VtfxError {
+    fn from(error: std::io::Error) -> Self {
+        VtfxError::Io(error)
+    }
+}
@@ -0,0 +1,101 @@
+use std::io::{Seek, Write};
+
+use image::codecs::hdr::HdrEncoder;
+use image::{DynamicImage, ImageOutputFormat, Rgb, Rgba, RgbaImage};
+
+use crate::error::VtfxError;
+
+///Default exposure (in stops) of tonemapped previews
+pub const DEFAULT_EXPOSURE: f32 = 0.0;
+
+///Write float image as OpenEXR
+pub fn write_exr<W: Write + Seek>(image: &DynamicImage, mut writer: W) -> Result<(), VtfxError>
+{
+    image.write_to(&mut writer, ImageOutputFormat::OpenExr)?;
+    Ok(())
+}
+
+///Write float image as Radiance hdr (alpha is not included)
+pub fn write_hdr<W: Write>(image: &DynamicImage, writer: W) -> Result<(), VtfxError>
+{
+    let rgb = image.to_rgb32f();
+    let pixels: Vec<Rgb<f32>> = rgb.pixels().copied().collect();
+    HdrEncoder::new(writer).encode(&pixels, rgb.width() as usize, rgb.height() as usize)?;
+    Ok(())
+}
+
+///Tonemap float image to 8 bit with reinhard, after scaling by exposure (in stops)
+pub fn tonemap(image: &DynamicImage, exposure: f32) -> DynamicImage
+{
+    let scale = exposure.exp2();
+    let rgba = image.to_rgba32f();
+    let tonemapped = RgbaImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let pixel = rgba.get_pixel(x, y);
+        let mut output = Rgba([0u8; 4]);
+        for channel in 0..3
+        {
+            let colour = (pixel[channel] * scale).max(0.0);
+            let mapped = (colour / (1.0 + colour)).powf(1.0 / 2.2);
+            output[channel] = (mapped * 255.0).round() as u8;
+        }
+        output[3] = (pixel[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+        output
+    });
+
+    DynamicImage::ImageRgba8(tonemapped)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use std::io::Cursor;
+    use image::codecs::hdr::HdrDecoder;
+    use image::Rgba32FImage;
+
+    fn float_image(pixels: &[[f32; 4]]) -> DynamicImage
+    {
+        let data = pixels.iter().flatten().copied().collect();
+        DynamicImage::ImageRgba32F(Rgba32FImage::from_raw(pixels.len() as u32, 1, data).unwrap())
+    }
+
+    #[test]
+    fn tonemap_is_reinhard_with_exposure()
+    {
+        let image = float_image(&[[0.0, 1.0, -1.0, 0.5], [3.0, 1000.0, 0.0, 2.0]]);
+        let tonemapped = tonemap(&image, DEFAULT_EXPOSURE).to_rgba8();
+        //1 maps to 0.5 before gamma, negative colour is clamped to 0 and alpha is clamped to 1
+        assert_eq!(tonemapped.get_pixel(0, 0).0, [0, 186, 0, 128]);
+        assert_eq!(tonemapped.get_pixel(1, 0).0, [224, 255, 0, 255]);
+
+        //One stop brighter doubles the colour before tonemapping
+        let brighter = tonemap(&float_image(&[[0.5, 0.5, 0.5, 1.0]]), 1.0).to_rgba8();
+        assert_eq!(brighter.get_pixel(0, 0).0, [186, 186, 186, 255]);
+    }
+
+    #[test]
+    fn hdr_output_is_readable()
+    {
+        let image = float_image(&[[0.5, 2.0, 16.0, 0.25], [0.0, 0.0, 0.0, 1.0]]);
+        let mut hdr = Vec::new();
+        write_hdr(&image, &mut hdr).unwrap();
+
+        let decoder = HdrDecoder::new(&hdr[..]).unwrap();
+        assert_eq!((decoder.metadata().width, decoder.metadata().height), (2, 1));
+        let pixels = decoder.read_image_hdr().unwrap();
+        //Radiance hdr has a shared 8 bit exponent, these values are exact
+        assert_eq!(pixels[0].0, [0.5, 2.0, 16.0]);
+        assert_eq!(pixels[1].0, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn exr_output_is_readable()
+    {
+        let image = float_image(&[[0.5, 2.0, 16.0, 0.25], [-1.0, 0.0, 1000.0, 1.0]]);
+        let mut exr = Cursor::new(Vec::new());
+        write_exr(&image, &mut exr).unwrap();
+
+        let read = image::load_from_memory_with_format(exr.get_ref(), image::ImageFormat::OpenExr).unwrap();
+        assert_eq!(read.to_rgba32f().as_raw(), image.to_rgba32f().as_raw());
+    }
+}
@@ -1,10 +1,10 @@
-use std::error::Error;
 use std::{collections::HashMap};
-use std::io;
 use once_cell::sync::Lazy;
 
 use num_enum::TryFromPrimitive;
 
+use crate::error::VtfxError;
+
 #[derive(Debug, Default, PartialEq, TryFromPrimitive, Eq, Hash, Copy, Clone)]
 #[repr(i32)]
 #[allow(non_camel_case_types, non_upper_case_globals)] //Keep enums same as source
@@ -80,15 +80,132 @@ pub enum ImageFormat
 }
 
 #[derive(Clone)]
+#[allow(non_camel_case_types)]
 pub struct image_format_info
 {
     ///Number of colour channels
     pub channels: u16,
     ///Depth of colours in bytes
     pub depth: u16,
-    ///Order of channels in relation to RGBA (0,1,2,3)
+    ///Order of channels in relation to RGBA (0,1,2,3). Each entry is the source channel of that output channel, or CHANNEL_NONE
     pub channel_order: Vec<u16>,
-    pub bc_format: Option<texpresso::Format>
+    pub bc_format: Option<texpresso::Format>,
+    ///Bit fields of 16 bit packed formats, which are unpacked to 8 bit RGBA before decoding
+    pub packed_format: Option<PackedFormat>,
+    ///RGBA value of output channels that are not read from the source
+    pub default_pixel: [u8; 4],
+    ///If this format only has luminance (and alpha), so can be exported as a grayscale image
+    pub grayscale: bool,
+    ///If channels are floats (half floats for depth 2)
+    pub float: bool,
+    ///If pure blue (0,0,255) pixels are transparent, for the BLUESCREEN formats
+    pub bluescreen: bool,
+    ///Output channels (RGBA) that are signed bytes, which are remapped from -128..127 to 0..255
+    pub signed_channels: [bool; 4],
+    ///If pixels are palette indices, which are expanded to 8 bit RGBA before decoding
+    pub paletted: bool,
+    ///Depth (and stencil) formats are decoded to grayscale depth
+    pub depth_format: Option<DepthFormat>,
+    ///For 4 byte formats whose console byte order differs from PC, the PC byte of each console byte. Each order is its own inverse
+    pub pc_byte_order: Option<[usize; 4]>
+}
+
+///Output channel is not read from the source, default_pixel is used instead
+pub const CHANNEL_NONE: u16 = u16::MAX;
+
+///Bit fields of a big endian 16 bit packed pixel format
+#[derive(Debug, Clone, Copy)]
+pub struct PackedFormat
+{
+    ///Shift and number of bits of red, green, blue and alpha. Channels with 0 bits are unpacked as 255
+    pub fields: [(u16, u16); 4]
+}
+
+impl PackedFormat
+{
+    pub const PIXEL_SIZE: usize = 2;
+
+    ///Unpack big endian packed pixels to 8 bit RGBA
+    pub fn unpack(&self, data: &[u8]) -> Vec<u8>
+    {
+        let mut unpacked = Vec::with_capacity((data.len() / Self::PIXEL_SIZE) * 4);
+        for pixel_bytes in data.chunks_exact(Self::PIXEL_SIZE)
+        {
+            let pixel = u16::from_be_bytes([pixel_bytes[0], pixel_bytes[1]]) as u32;
+            for (shift, bits) in self.fields
+            {
+                if bits == 0
+                {
+                    unpacked.push(255);
+                    continue;
+                }
+
+                //Scale channel up to 8 bits
+                let max = (1u32 << bits) - 1;
+                let value = (pixel >> shift) & max;
+                unpacked.push(((value * 255 + (max / 2)) / max) as u8);
+            }
+        }
+
+        unpacked
+    }
+}
+
+///Layout of a depth-stencil format, stencil is ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFormat
+{
+    ///16 bit depth (big endian u16)
+    D16,
+    ///24 bit depth in the upper bits of a big endian u32, the lower 8 bits are stencil
+    D24,
+    ///24 bit float depth (20 bit mantissa, 4 bit exponent) in the upper bits of a big endian u32, the lower 8 bits are stencil
+    D24F
+}
+
+impl DepthFormat
+{
+    pub fn get_pixel_size(&self) -> usize
+    {
+        match self
+        {
+            DepthFormat::D16 => 2,
+            DepthFormat::D24 | DepthFormat::D24F => 4
+        }
+    }
+
+    ///Read depth of a big endian pixel, integer depths are normalised to 0-1
+    pub fn read(&self, pixel: &[u8]) -> f32
+    {
+        match self
+        {
+            DepthFormat::D16 => u16::from_be_bytes([pixel[0], pixel[1]]) as f32 / u16::MAX as f32,
+            DepthFormat::D24 => {
+                let depth = u32::from_be_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]) >> 8;
+                depth as f32 / 0xFFFFFF as f32
+            },
+            DepthFormat::D24F => float20e4_to_f32(u32::from_be_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]) >> 8)
+        }
+    }
+
+    ///If depth is a float, so may be outside of 0-1
+    pub fn is_float(&self) -> bool
+    {
+        *self == DepthFormat::D24F
+    }
+}
+
+///Convert xbox 360 20e4 float (exponent bias of 15, no sign) to f32
+fn float20e4_to_f32(value: u32) -> f32
+{
+    let mantissa = (value & 0xFFFFF) as f32 / (1 << 20) as f32;
+    let exponent = ((value >> 20) & 0xF) as i32;
+    match exponent
+    {
+        //Denormal
+        0 => mantissa * 2f32.powi(-14),
+        _ => (1.0 + mantissa) * 2f32.powi(exponent - 15)
+    }
 }
 
 impl image_format_info
@@ -96,51 +213,148 @@ impl image_format_info
     fn new(channels: u16, depth: u16, channel_order: Vec<u16>) -> image_format_info
     {
         image_format_info {
-            channels: channels,
-            depth: depth,
-            channel_order: channel_order,
-            bc_format: None
+            channels,
+            depth,
+            channel_order,
+            bc_format: None,
+            packed_format: None,
+            default_pixel: [255; 4],
+            grayscale: false,
+            float: false,
+            bluescreen: false,
+            signed_channels: [false; 4],
+            paletted: false,
+            depth_format: None,
+            pc_byte_order: None
+        }
+    }
+
+    ///4 byte format with the console channel_order, whose bytes are moved to pc_byte_order when converting to a PC vtf
+    fn new_with_pc_byte_order(channel_order: Vec<u16>, pc_byte_order: [usize; 4]) -> image_format_info
+    {
+        image_format_info {
+            pc_byte_order: Some(pc_byte_order),
+            ..image_format_info::new(4, 1, channel_order)
+        }
+    }
+
+    ///4 byte formats stored as a big endian u32 on PS3 and xbox 360 (e.g. BGRA8888 is A,R,G,B in memory).
+    ///channel_order is the PC (little endian) byte order, which is reversed.
+    fn new_big_endian_32(channel_order: Vec<u16>) -> image_format_info
+    {
+        image_format_info::new_with_pc_byte_order(channel_order.iter().map(|channel| 3 - channel).collect(), [3, 2, 1, 0])
+    }
+
+    ///Signed bump/dudv formats, channel_order is the PC byte order (same as new_big_endian_32)
+    fn new_signed(channel_order: Vec<u16>, signed_channels: [bool; 4]) -> image_format_info
+    {
+        image_format_info {
+            signed_channels,
+            ..image_format_info::new_big_endian_32(channel_order)
+        }
+    }
+
+    ///Palettized formats have a single 8 bit index per pixel
+    fn new_paletted() -> image_format_info
+    {
+        image_format_info {
+            paletted: true,
+            ..image_format_info::new(1, 1, vec![0,1,2,3])
+        }
+    }
+
+    ///Depth formats have a single depth channel, any stencil is ignored
+    fn new_with_depth(depth_format: DepthFormat) -> image_format_info
+    {
+        image_format_info {
+            depth_format: Some(depth_format),
+            ..image_format_info::new(1, depth_format.get_pixel_size() as u16, vec![0])
+        }
+    }
+
+    ///Bluescreen formats are 3 byte RGB where pure blue is decoded as transparent
+    fn new_bluescreen(channel_order: Vec<u16>) -> image_format_info
+    {
+        image_format_info {
+            bluescreen: true,
+            ..image_format_info::new(3, 1, channel_order)
+        }
+    }
+
+    ///Float formats are decoded to 32 bit float RGBA
+    fn new_float(channels: u16, depth: u16, channel_order: Vec<u16>, default_pixel: [u8; 4]) -> image_format_info
+    {
+        image_format_info {
+            float: true,
+            ..image_format_info::new_with_default_pixel(channels, depth, channel_order, default_pixel)
+        }
+    }
+
+    fn new_with_default_pixel(channels: u16, depth: u16, channel_order: Vec<u16>, default_pixel: [u8; 4]) -> image_format_info
+    {
+        image_format_info {
+            default_pixel,
+            ..image_format_info::new(channels, depth, channel_order)
+        }
+    }
+
+    ///Luminance is broadcast to RGB
+    fn new_grayscale(channels: u16, channel_order: Vec<u16>) -> image_format_info
+    {
+        image_format_info {
+            grayscale: true,
+            ..image_format_info::new(channels, 1, channel_order)
         }
     }
 
     fn new_with_bc(channels: u16, depth: u16, channel_order: Vec<u16>, bc_format: Option<texpresso::Format>) -> image_format_info
     {
         image_format_info {
-            channels: channels,
-            depth: depth,
-            channel_order: channel_order,
-            bc_format: bc_format
+            bc_format,
+            ..image_format_info::new(channels, depth, channel_order)
+        }
+    }
+
+    ///Packed formats are unpacked to 8 bit RGBA, channels is 3 if alpha is not used
+    fn new_with_packed(channels: u16, fields: [(u16, u16); 4]) -> image_format_info
+    {
+        image_format_info {
+            packed_format: Some(PackedFormat { fields }),
+            ..image_format_info::new(channels, 1, vec![0,1,2,3])
         }
     }
 
-    pub fn try_get_bc_format(&self) -> Result<texpresso::Format, Box<dyn Error>>
+    ///Get size of a single pixel in bytes, not valid for dxt formats
+    pub fn get_pixel_size(&self) -> usize
     {
-        let bc_format = self.bc_format.ok_or("Image format is not DXT")?;
-        Ok(bc_format)
+        match self.packed_format
+        {
+            Some(_) => PackedFormat::PIXEL_SIZE,
+            None => (self.depth * self.channels) as usize
+        }
     }
 
     ///Get expected size for a resource in this format
     pub fn get_expected_size(&self, width: &usize, height: &usize) -> usize
     {
-        if self.bc_format.is_some()
+        if let Some(bc_format) = self.bc_format
         {
-            self.bc_format.unwrap().compressed_size(*width, *height)
+            bc_format.compressed_size(*width, *height)
         }
         else
         {
-            let single_pixel_size = self.depth * self.channels;
-            width * height * single_pixel_size as usize
+            width * height * self.get_pixel_size()
         }
     }
 }
 
 ///Convert endianness of dxt bc data (big to little)
-pub fn correct_dxt_endianness(format: &texpresso::Format, data: &mut [u8]) -> Result<(), Box<dyn Error>>
+pub fn correct_dxt_endianness(format: &texpresso::Format, data: &mut [u8]) -> Result<(), VtfxError>
 {
     //https://learn.microsoft.com/en-us/windows/win32/direct3d10/d3d10-graphics-programming-guide-resources-block-compression#bc1
 
     ///Fix single block of bc1 data
-    fn fix_bc1(block: &mut [u8]) -> Result<(), Box<dyn Error>>
+    fn fix_bc1(block: &mut [u8])
     {
         let rgb_index: usize = 0;
         let bitmap_index = rgb_index + 4;
@@ -149,25 +363,18 @@ pub fn correct_dxt_endianness(format: &texpresso::Format, data: &mut [u8]) -> Re
         for i in 0..2
         {
             let index = rgb_index + (2 * i);
-            let u16 = u16::from_be_bytes(block[index..index + 2].try_into()?);
+            let u16 = u16::from_be_bytes([block[index], block[index + 1]]);
             let u16_bytes = u16.to_le_bytes();
-            
-            for j in 0..u16_bytes.len()
-            {
-                block[index + j] = u16_bytes[j];
-            }
+            block[index..index + 2].copy_from_slice(&u16_bytes);
         }
 
         //bitmap u32 fix (treat as u16[2])
         for i in 0..2
         {
             let index = bitmap_index + (i * 2);
-            let bitmap: u16 = u16::from_be_bytes(block[index..index + 2].try_into()?);
+            let bitmap: u16 = u16::from_be_bytes([block[index], block[index + 1]]);
             let bitmap_bytes = bitmap.to_le_bytes();
-            for x in 0..2
-            {
-                block[index + x] = bitmap_bytes[x];
-            }
+            block[index..index + 2].copy_from_slice(&bitmap_bytes);
         }
         
         //Reverse bitmap indexes
@@ -183,45 +390,111 @@ pub fn correct_dxt_endianness(format: &texpresso::Format, data: &mut [u8]) -> Re
             block[bitmap_index + i] = new_byte;
         }
         */
-
-        Ok(())
     }
 
     match format {
+        texpresso::Format::Bc5 =>
+        {
+            if !data.len().is_multiple_of(16)
+            {
+                return Err(VtfxError::InvalidBlockLength { block_size: 16, length: data.len() });
+            }
+
+            //Both channel blocks are swapped as u16, same as bc1
+            for block in data.chunks_exact_mut(8)
+            {
+                fix_bc1(block);
+            }
+        },
+        texpresso::Format::Bc2 =>
+        {
+            if !data.len().is_multiple_of(16)
+            {
+                return Err(VtfxError::InvalidBlockLength { block_size: 16, length: data.len() });
+            }
+
+            for block in data.chunks_exact_mut(16)
+            {
+                //Explicit alpha is 4 rows of u16, needs the same u16 swap as bc1
+                fix_bc1(&mut block[0..8]);
+                fix_bc1(&mut block[8..16]);
+            }
+        },
         texpresso::Format::Bc3 => 
         {
-            if data.len() % 16 != 0
+            if !data.len().is_multiple_of(16)
             {
-                let err = io::Error::new(io::ErrorKind::Other, format!("Length of dxt5 buffer should be multiple of 16. Length: {}", data.len()));
-                return Err(Box::new(err));
+                return Err(VtfxError::InvalidBlockLength { block_size: 16, length: data.len() });
             }
 
             for block in data.chunks_exact_mut(16)
             {
-                //Use bc1 fix on the last part of the block
-                fix_bc1(&mut block[8..16])?;
+                //Alpha and colour halves are both stored as big endian u16's, same as bc1
+                fix_bc1(&mut block[0..8]);
+                fix_bc1(&mut block[8..16]);
             }
         },
-        texpresso::Format::Bc1 =>
+        texpresso::Format::Bc1 | texpresso::Format::Bc4 =>
         {
-            if data.len() % 8 != 0
+            if !data.len().is_multiple_of(8)
             {
-                let err = io::Error::new(io::ErrorKind::Other, format!("Length of dxt1 buffer should be multiple of 8. Length: {}", data.len()));
-                return Err(Box::new(err));
+                return Err(VtfxError::InvalidBlockLength { block_size: 8, length: data.len() });
             }
 
             for block in data.chunks_exact_mut(8)
             {
-                fix_bc1(block)?;
+                fix_bc1(block);
             }
         }
-        _ =>
+    };
+
+    Ok(())
+}
+
+///Convert raw (not decoded) image data between console (big endian) and PC (little endian) byte order, converting twice undoes it.
+///fix_dxt should be true for xbox 360 data, ps3 dxt data is already little endian.
+pub fn correct_endianness(format: ImageFormat, data: &mut [u8], fix_dxt: bool) -> Result<(), VtfxError>
+{
+    let format_info = format.try_get_format_info()?;
+    if let Some(bc_format) = format_info.bc_format
+    {
+        if fix_dxt
+        {
+            correct_dxt_endianness(&bc_format, data)?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(pc_byte_order) = format_info.pc_byte_order
+    {
+        for pixel in data.chunks_exact_mut(4)
         {
-            let err = io::Error::new(io::ErrorKind::Other, format!("endianness fix not implemented for dxt bc format: {:?}", format));
-            return Err(Box::new(err));
+            let source = [pixel[0], pixel[1], pixel[2], pixel[3]];
+            for (byte, source_byte) in pixel.iter_mut().zip(pc_byte_order)
+            {
+                *byte = source[source_byte];
+            }
         }
+
+        return Ok(());
+    }
+
+    let swap_size = match (format_info.packed_format, format_info.depth_format)
+    {
+        (Some(_), _) => PackedFormat::PIXEL_SIZE,
+        (None, Some(depth_format)) => depth_format.get_pixel_size(),
+        (None, None) => format_info.depth as usize
     };
 
+    if swap_size > 1
+    {
+        for value in data.chunks_exact_mut(swap_size)
+        {
+            value.reverse();
+        }
+    }
+
     Ok(())
 }
 
@@ -266,23 +539,29 @@ pub fn GetNumMipMapLevels(mut width: i32, mut height: i32, mut depth: i32) -> i3
 		}
 		numMipLevels += 1;
 	}
-	return numMipLevels;
+	numMipLevels
 }
 
-pub fn GetMipMapLevelByteOffset(mut width: i32, mut height: i32, image_format: &image_format_info, mut skip_mip_levels: i32) -> usize
+#[allow(non_snake_case)]
+pub fn GetMipMapLevelByteOffset(mut width: i32, mut height: i32, mut depth: i32, image_format: &image_format_info, mut skip_mip_levels: i32) -> usize
 {
 	let mut offset: usize = 0;
+	if depth < 1
+	{
+		depth = 1;
+	}
 
 	while skip_mip_levels > 0
 	{
-		offset += (width * height * (image_format.depth * image_format.channels) as i32) as usize;
-		if width == 1 && height == 1
+		offset += image_format.get_expected_size(&(width as usize), &(height as usize)) * depth as usize;
+		if width == 1 && height == 1 && depth == 1
 		{
 			break;
 		}
 
 		width >>= 1;
 		height >>= 1;
+		depth >>= 1;
 		if width < 1 
 		{
 			width = 1;
@@ -291,45 +570,136 @@ pub fn GetMipMapLevelByteOffset(mut width: i32, mut height: i32, image_format: &
 		{
 			height = 1;
 		}
+		if depth < 1
+		{
+			depth = 1;
+		}
 		skip_mip_levels -= 1;
 	}
-	return offset;
+	offset
 }
 
 static IMAGE_FORMAT_INFO_MAP: Lazy<HashMap<ImageFormat, image_format_info>> = Lazy::new(|| {
     let mut map = HashMap::new();
-    map.reserve(12);
     map.insert(ImageFormat::IMAGE_FORMAT_DXT1, image_format_info::new_with_bc(3, 1, vec![0,1,2], Option::from(texpresso::Format::Bc1)));
+    map.insert(ImageFormat::IMAGE_FORMAT_DXT3, image_format_info::new_with_bc(4, 1, vec![0,1,2,3], Option::from(texpresso::Format::Bc2)));
     map.insert(ImageFormat::IMAGE_FORMAT_DXT5, image_format_info::new_with_bc(4, 1, vec![0,1,2,3], Option::from(texpresso::Format::Bc3)));
     map.insert(ImageFormat::IMAGE_FORMAT_DXT1_ONEBITALPHA, image_format_info::new_with_bc(4, 1, vec![0,1,2,3], Option::from(texpresso::Format::Bc1)));
+    map.insert(ImageFormat::IMAGE_FORMAT_ATI2N, image_format_info::new_with_bc(3, 1, vec![0,1,2], Option::from(texpresso::Format::Bc5)));
+    map.insert(ImageFormat::IMAGE_FORMAT_ATI1N, image_format_info::new_with_bc(3, 1, vec![0,1,2], Option::from(texpresso::Format::Bc4)));
+    //Packed formats are named from the lowest bits
+    map.insert(ImageFormat::IMAGE_FORMAT_RGB565, image_format_info::new_with_packed(3, [(0, 5), (5, 6), (11, 5), (0, 0)]));
+    map.insert(ImageFormat::IMAGE_FORMAT_BGR565, image_format_info::new_with_packed(3, [(11, 5), (5, 6), (0, 5), (0, 0)]));
+    map.insert(ImageFormat::IMAGE_FORMAT_BGRA4444, image_format_info::new_with_packed(4, [(8, 4), (4, 4), (0, 4), (12, 4)]));
+    map.insert(ImageFormat::IMAGE_FORMAT_BGRX5551, image_format_info::new_with_packed(3, [(10, 5), (5, 5), (0, 5), (0, 0)]));
+    map.insert(ImageFormat::IMAGE_FORMAT_BGRA5551, image_format_info::new_with_packed(4, [(10, 5), (5, 5), (0, 5), (15, 1)]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_BGRX5551, image_format_info::new_with_packed(3, [(10, 5), (5, 5), (0, 5), (0, 0)]));
+    map.insert(ImageFormat::IMAGE_FORMAT_RGBA16161616F, image_format_info::new_float(4, 2, vec![0,1,2,3], [255; 4]));
+    map.insert(ImageFormat::IMAGE_FORMAT_R32F, image_format_info::new_float(1, 4, vec![0], [0, 0, 0, 255]));
+    map.insert(ImageFormat::IMAGE_FORMAT_RGB323232F, image_format_info::new_float(3, 4, vec![0,1,2], [255; 4]));
+    map.insert(ImageFormat::IMAGE_FORMAT_RGBA32323232F, image_format_info::new_float(4, 4, vec![0,1,2,3], [255; 4]));
+    map.insert(ImageFormat::IMAGE_FORMAT_I8, image_format_info::new_grayscale(1, vec![0,0,0]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_I8, image_format_info::new_grayscale(1, vec![0,0,0]));
+    map.insert(ImageFormat::IMAGE_FORMAT_IA88, image_format_info::new_grayscale(2, vec![0,0,0,1]));
+    map.insert(ImageFormat::IMAGE_FORMAT_A8, image_format_info::new_with_default_pixel(1, 1, vec![CHANNEL_NONE,CHANNEL_NONE,CHANNEL_NONE,0], [0, 0, 0, 255]));
+    map.insert(ImageFormat::IMAGE_FORMAT_UV88, image_format_info::new_with_default_pixel(2, 1, vec![0,1], [0, 0, 0, 255]));
     map.insert(ImageFormat::IMAGE_FORMAT_RGBA16161616, image_format_info::new(4, 2, vec![0,1,2,3]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_RGBA16161616, image_format_info::new(4, 2, vec![0,1,2,3]));
+    //PS3 and xbox 360 share the same byte order for these, only DXT word order and tiling/swizzling differ between them.
+    //BGRA8888 is D3DFMT_A8R8G8B8, a big endian u32 on console so it is stored as A,R,G,B.
+    //The orders of BGRX8888, RGBA8888, ABGR8888 and ARGB8888 are unverified, no console sample or format definition has confirmed them
     map.insert(ImageFormat::IMAGE_FORMAT_BGRX8888, image_format_info::new(4, 1, vec![2,1,0,3]));
     map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_BGRX8888, image_format_info::new(4, 1, vec![2,1,0,3]));
     map.insert(ImageFormat::IMAGE_FORMAT_RGBA8888, image_format_info::new(4, 1, vec![0,1,2,3]));
     map.insert(ImageFormat::IMAGE_FORMAT_ABGR8888, image_format_info::new(4, 1, vec![3,2,1,0]));
+    //Read as A,B,G,R on console, so R and B swap places with PC
+    map.insert(ImageFormat::IMAGE_FORMAT_ARGB8888, image_format_info::new_with_pc_byte_order(vec![3,2,1,0], [0, 3, 2, 1]));
+    map.insert(ImageFormat::IMAGE_FORMAT_BGRA8888, image_format_info::new_big_endian_32(vec![2,1,0,3]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_RGBA8888, image_format_info::new(4, 1, vec![0,1,2,3]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_ABGR8888, image_format_info::new(4, 1, vec![3,2,1,0]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_ARGB8888, image_format_info::new_with_pc_byte_order(vec![3,2,1,0], [0, 3, 2, 1]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888, image_format_info::new_big_endian_32(vec![2,1,0,3]));
+    map.insert(ImageFormat::IMAGE_FORMAT_UVWQ8888, image_format_info::new_signed(vec![0,1,2,3], [true; 4]));
+    //L is unsigned luminance, X is unused
+    map.insert(ImageFormat::IMAGE_FORMAT_UVLX8888, image_format_info::new_signed(vec![0,1,2,3], [true, true, false, false]));
+    //LE formats are already stored in PC byte order
+    map.insert(ImageFormat::IMAGE_FORMAT_LE_BGRX8888, image_format_info::new(4, 1, vec![2,1,0,3]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LE_BGRA8888, image_format_info::new(4, 1, vec![2,1,0,3]));
+    //3 byte formats are not stored as a single value so are the same as PC
     map.insert(ImageFormat::IMAGE_FORMAT_RGB888, image_format_info::new(3, 1, vec![0,1,2]));
     map.insert(ImageFormat::IMAGE_FORMAT_BGR888, image_format_info::new(3, 1, vec![2,1,0]));
-    map.insert(ImageFormat::IMAGE_FORMAT_ARGB8888, image_format_info::new(4, 1, vec![3,2,1,0]));
-    map.insert(ImageFormat::IMAGE_FORMAT_BGRA8888, image_format_info::new(4, 1, vec![1,2,3,0])); //Sometimes is G,B,A,R?
-    return map;
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_RGB888, image_format_info::new(3, 1, vec![0,1,2]));
+    map.insert(ImageFormat::IMAGE_FORMAT_LINEAR_BGR888, image_format_info::new(3, 1, vec![2,1,0]));
+    map.insert(ImageFormat::IMAGE_FORMAT_P8, image_format_info::new_paletted());
+    map.insert(ImageFormat::IMAGE_FORMAT_X360_DST16, image_format_info::new_with_depth(DepthFormat::D16));
+    map.insert(ImageFormat::IMAGE_FORMAT_X360_DST24, image_format_info::new_with_depth(DepthFormat::D24));
+    map.insert(ImageFormat::IMAGE_FORMAT_X360_DST24F, image_format_info::new_with_depth(DepthFormat::D24F));
+    map.insert(ImageFormat::IMAGE_FORMAT_NV_DST16, image_format_info::new_with_depth(DepthFormat::D16));
+    map.insert(ImageFormat::IMAGE_FORMAT_NV_DST24, image_format_info::new_with_depth(DepthFormat::D24));
+    map.insert(ImageFormat::IMAGE_FORMAT_ATI_DST16, image_format_info::new_with_depth(DepthFormat::D16));
+    map.insert(ImageFormat::IMAGE_FORMAT_ATI_DST24, image_format_info::new_with_depth(DepthFormat::D24));
+    //INTZ and RAWZ are read as 24 bit depth with 8 bit stencil, NV_NULL has no data
+    map.insert(ImageFormat::IMAGE_FORMAT_NV_INTZ, image_format_info::new_with_depth(DepthFormat::D24));
+    map.insert(ImageFormat::IMAGE_FORMAT_NV_RAWZ, image_format_info::new_with_depth(DepthFormat::D24));
+    map.insert(ImageFormat::IMAGE_FORMAT_RGB888_BLUESCREEN, image_format_info::new_bluescreen(vec![0,1,2]));
+    map.insert(ImageFormat::IMAGE_FORMAT_BGR888_BLUESCREEN, image_format_info::new_bluescreen(vec![2,1,0]));
+    map
 });
 
 impl ImageFormat
 {
-    pub fn get_format_info(&self) -> Option<&image_format_info>
+    pub fn get_format_info(&self) -> Option<&'static image_format_info>
     {
-        let num = *self as i32;
-        let format_info = IMAGE_FORMAT_INFO_MAP.get(&self);
-        if format_info.is_some() && num >= 30
+        IMAGE_FORMAT_INFO_MAP.get(self)
+    }
+
+    ///If this is one of the LINEAR formats, which are never tiled on xbox 360 (or swizzled on PS3)
+    pub fn is_linear(&self) -> bool
+    {
+        matches!(self,
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGRX8888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_RGBA8888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_ABGR8888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_ARGB8888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_RGB888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGR888 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGRX5551 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_I8 |
+            ImageFormat::IMAGE_FORMAT_LINEAR_RGBA16161616)
+    }
+
+    ///If this is one of the LE formats, which are stored in PC byte order on xbox 360
+    pub fn is_little_endian(&self) -> bool
+    {
+        matches!(self, ImageFormat::IMAGE_FORMAT_LE_BGRX8888 | ImageFormat::IMAGE_FORMAT_LE_BGRA8888)
+    }
+
+    ///Get the format to use for this format in a PC vtf. LINEAR and LE formats are console only so use their PC equivalent.
+    ///None if there is no PC equivalent (xbox 360 depth formats).
+    pub fn get_pc_format(&self) -> Option<ImageFormat>
+    {
+        let pc_format = match self
         {
-            println!("!!Warning!! The image format '{:?}' is untested. If result is garbage please open an issue on github.", &self);
-        }
-        format_info
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGRX8888 | ImageFormat::IMAGE_FORMAT_LE_BGRX8888 => ImageFormat::IMAGE_FORMAT_BGRX8888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888 | ImageFormat::IMAGE_FORMAT_LE_BGRA8888 => ImageFormat::IMAGE_FORMAT_BGRA8888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_RGBA8888 => ImageFormat::IMAGE_FORMAT_RGBA8888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_ABGR8888 => ImageFormat::IMAGE_FORMAT_ABGR8888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_ARGB8888 => ImageFormat::IMAGE_FORMAT_ARGB8888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_RGB888 => ImageFormat::IMAGE_FORMAT_RGB888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGR888 => ImageFormat::IMAGE_FORMAT_BGR888,
+            ImageFormat::IMAGE_FORMAT_LINEAR_BGRX5551 => ImageFormat::IMAGE_FORMAT_BGRX5551,
+            ImageFormat::IMAGE_FORMAT_LINEAR_I8 => ImageFormat::IMAGE_FORMAT_I8,
+            ImageFormat::IMAGE_FORMAT_LINEAR_RGBA16161616 => ImageFormat::IMAGE_FORMAT_RGBA16161616,
+            ImageFormat::IMAGE_FORMAT_X360_DST16 | ImageFormat::IMAGE_FORMAT_X360_DST24 | ImageFormat::IMAGE_FORMAT_X360_DST24F => return None,
+            _ => *self
+        };
+
+        Some(pc_format)
     }
 
-    pub fn try_get_format_info(&self) -> Result<&image_format_info, Box<dyn Error>>
+    pub fn try_get_format_info(&self) -> Result<&'static image_format_info, VtfxError>
     {
-        let image_format = self.get_format_info().ok_or("vtfx is an unsupported/unknown format")?;
-        Ok(image_format)
+        self.get_format_info().ok_or(VtfxError::UnsupportedFormat(*self))
     }
 }
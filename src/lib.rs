@@ -0,0 +1,33 @@
+//! Library for parsing and decoding VTFX files (Source engine PS3/Xbox 360 VTF)
+//!
+//! Use [`decode_image`] to decode the main image of a vtfx file, or [`VTFXHEADER::from`] and [`resource_to_image`] for more control.
+
+pub mod vtfx;
+pub mod image_format;
+pub mod resource_entry_info;
+pub mod resource;
+pub mod decode;
+pub mod error;
+pub mod animation;
+pub mod cubemap;
+pub mod volume;
+pub mod untile;
+pub mod swizzle;
+pub mod hdr;
+pub mod palette;
+pub mod vtf_writer;
+pub mod vtfx_writer;
+
+//Lets test_util use vtfx_reader paths, as it does when included by the integration tests
+#[cfg(test)]
+extern crate self as vtfx_reader;
+#[cfg(test)]
+mod test_util;
+
+pub use vtfx::VTFXHEADER;
+pub use image_format::ImageFormat;
+pub use resource_entry_info::{ResourceEntryInfo, ResourceType};
+pub use resource::Resource;
+pub use decode::{DecodeOptions, MipIterator, MipLevel, decode_image, decode_low_res_image, resource_to_image, resource_to_mips, low_res_resource_to_image, low_res_resource_to_data};
+pub use error::VtfxError;
+pub use palette::Palette;
@@ -3,31 +3,28 @@ use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::BufReader;
+use std::io::BufWriter;
 use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::exit;
-use args::Args;
+use args::{AnimationExport, Args, CubemapExport, HdrExport, LowResExport, VolumeExport, VtfxExport, VtfxImageFormat};
 use clap::Parser;
-use image::DynamicImage;
-use image::GenericImage;
-use image::Rgba;
-use image_format::correct_dxt_endianness;
+use image::{DynamicImage, Rgba, RgbaImage};
 use once_cell::sync::Lazy;
-use vtfx::VTFXHEADER;
-use std::convert::TryInto;
+use vtfx_reader::animation;
+use vtfx_reader::cubemap::{self, CubemapFace};
+use vtfx_reader::decode::is_lzma_compressed;
+use vtfx_reader::hdr;
+use vtfx_reader::palette;
+use vtfx_reader::volume;
+use vtfx_reader::vtf_writer;
+use vtfx_reader::vtfx_writer::{self, PcTexture, VtfxPlatform};
+use vtfx_reader::{DecodeOptions, ImageFormat, MipIterator, MipLevel, Palette, Resource, ResourceEntryInfo, VTFXHEADER, low_res_resource_to_image, resource_to_mips};
 
-use crate::image_format::ImageFormat;
-use crate::resource_entry_info::ResourceEntryInfo;
-use crate::vtfx::VTF_LEGACY_RSRC_IMAGE;
-
-mod vtfx;
-mod image_format;
-mod resource_entry_info;
 mod args;
 
-const LZMA_MAGIC: &[u8;4] = b"LZMA";
-static ARGS: Lazy<Args> = Lazy::new(|| { Args::parse() });
+static ARGS: Lazy<Args> = Lazy::new(Args::parse);
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
@@ -49,21 +46,43 @@ fn main() {
     if path.is_file()
     {
         println!("Opening '{}'...", path.to_string_lossy());
-        match read_vtfx(&path) {
-            Ok(_) => {println!("VTFX processing complete")},
-            Err(e) => {println!("Failed to open file: {e}")},
-        };
+        process_file(path);
     }
     else if path.is_dir()
     {
         println!("Will open all vtf files in given folder");
-        match read_all_vtfx_in_folder(&path) {
+        match read_all_vtfx_in_folder(path) {
             Ok(_) => {},
             Err(e) => {println!("Failed to process all files in input folder: {e}")},
         }
     }
 }
 
+///Build decode options from command line arguments
+fn get_decode_options() -> Result<DecodeOptions, Box<dyn Error>>
+{
+    let palette = match ARGS.palette.as_ref()
+    {
+        Some(palette_path) => Some(Palette::from_bytes(&fs::read(palette_path)?)?),
+        None => None
+    };
+
+    Ok(DecodeOptions {
+        export_alpha: ARGS.export_alpha,
+        force_dxt_endian_fix: ARGS.force_dxt_endian_fix,
+        onebit_alpha: ARGS.experimental_onebitalpha,
+        no_untile: ARGS.no_untile,
+        reconstruct_z: ARGS.reconstruct_z,
+        uv_blue: ARGS.uv_blue,
+        no_grayscale: ARGS.no_grayscale,
+        eight_bit: ARGS.eight_bit,
+        no_deswizzle: ARGS.no_deswizzle,
+        raw_signed: ARGS.raw_signed,
+        keep_bluescreen: ARGS.keep_bluescreen,
+        palette,
+    })
+}
+
 fn read_all_vtfx_in_folder(path: &Path) -> Result<(), Box<dyn Error>>
 {
     for file in fs::read_dir(path)?
@@ -76,16 +95,62 @@ fn read_all_vtfx_in_folder(path: &Path) -> Result<(), Box<dyn Error>>
         else
         {
             println!("Opening '{}'...", path.to_string_lossy());
-            match read_vtfx(&path) {
-                Ok(_) => {println!("VTFX processing complete")},
-                Err(e) => {println!("Failed to open file: {e}")},
-            };
+            process_file(&path);
         }
     }
 
     Ok(())
 }
 
+///Read the vtfx file at path, or convert it to a vtfx if enabled
+fn process_file(path: &Path)
+{
+    match ARGS.to_vtfx
+    {
+        Some(platform) => match convert_to_vtfx(path, platform) {
+            Ok(_) => {println!("VTFX conversion complete")},
+            Err(e) => {println!("Failed to convert file: {e}")},
+        },
+        None => match read_vtfx(path) {
+            Ok(_) => {println!("VTFX processing complete")},
+            Err(e) => {println!("Failed to open file: {e}")},
+        }
+    };
+}
+
+///Convert a PC vtf or an image (e.g. png) at path to a vtfx
+fn convert_to_vtfx(path: &Path, platform: VtfxExport) -> Result<(), Box<dyn Error>>
+{
+    let buffer = fs::read(path)?;
+    let texture = match buffer.starts_with(b"VTF\0")
+    {
+        true => PcTexture::from_vtf(&buffer)?,
+        false => {
+            let image_format = match ARGS.vtfx_format
+            {
+                VtfxImageFormat::Bgra8888 => ImageFormat::IMAGE_FORMAT_BGRA8888,
+                VtfxImageFormat::Bgr888 => ImageFormat::IMAGE_FORMAT_BGR888,
+                VtfxImageFormat::Dxt1 => ImageFormat::IMAGE_FORMAT_DXT1,
+                VtfxImageFormat::Dxt5 => ImageFormat::IMAGE_FORMAT_DXT5
+            };
+            PcTexture::from_image(&image::load_from_memory(&buffer)?, image_format, !ARGS.vtfx_no_mips)?
+        }
+    };
+
+    println!("    Image format: {:?}, w: {}, h: {}, mips: {}, frames: {}", texture.image_format, texture.width, texture.height, texture.mip_count, texture.frames);
+
+    let (platform, extension) = match platform
+    {
+        VtfxExport::X360 => (VtfxPlatform::Xbox360, "360.vtf"),
+        VtfxExport::Ps3 => (VtfxPlatform::Ps3, "vtf")
+    };
+    let save_path = get_save_path(path, "vtfx", extension);
+    vtfx_writer::write_vtfx(&texture, platform, BufWriter::new(File::create(&save_path)?))?;
+    println!("    ✅ Saved vtfx to '{}'", save_path.as_path().to_string_lossy());
+
+    Ok(())
+}
+
 ///Open a vtfx file at path and read its data
 fn read_vtfx(path: &Path) -> Result<VTFXHEADER, Box<dyn Error>>
 {
@@ -122,280 +187,358 @@ fn read_vtfx(path: &Path) -> Result<VTFXHEADER, Box<dyn Error>>
 
     println!("{}", vtfx);
 
+    let decode_options = get_decode_options()?;
+
+    if !ARGS.no_resource_export && ARGS.low_res != LowResExport::None
+    {
+        let sample = vtfx.low_res_image_sample;
+        println!("Low res sample colour: {:?}", sample);
+        let sample_image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba(sample)));
+        save_image(&sample_image, path, "low_res_sample")?;
+    }
+
     let mut res_num = 0;
-    let resource_entry_infos = vtfx.get_resource_entry_infos(&buffer);
-    for resource in resource_entry_infos
+    let resource_entry_infos = vtfx.get_resource_entry_infos(&buffer)?;
+    for (entry_num, resource_entry_info) in resource_entry_infos.iter().enumerate()
     {
-        println!("Reading resource #{}. Type: {:?}, Start: {}", res_num, resource.chTypeBytes, resource.resData);
+        println!("Reading resource #{}. Type: {:?}, Flags: {:#04x}, Data: {}", entry_num, resource_entry_info.get_type(), resource_entry_info.get_flags(), resource_entry_info.resData);
+
+        let resource = match Resource::parse(&buffer, resource_entry_info, entry_num) {
+            Ok(resource) => resource,
+            Err(error) => {
+                println!("    ❌ Error reading resource {}: {}", entry_num, error);
+                continue;
+            }
+        };
+
+        println!("    {}", resource);
 
-        //Is this resource a high res image?
-        if resource.chTypeBytes == VTF_LEGACY_RSRC_IMAGE
+        match resource
         {
-            println!("    Type is VTF_LEGACY_RSRC_IMAGE");
+            //Is this resource a high res image?
+            Resource::Image { .. } => {
+                if !ARGS.no_resource_export && ARGS.low_res != LowResExport::Only
+                {
+                    print_image_resource_info(&buffer, resource_entry_info.resData as usize, &vtfx, &decode_options, res_num);
 
-            if !ARGS.no_resource_export
-            {
-                match resource_to_image(&buffer, &resource, &vtfx, &res_num) {
-                    Ok(image) => {
-                        let filename = path.file_stem().unwrap().to_str().unwrap();
-                        let new_image_name = format!("{filename}_resource_{res_num}.png");
-                        let save_path = &match ARGS.output.is_some()
-                        {
-                            true => ARGS.output.as_ref().unwrap().clone().join(&new_image_name),
-                            false => PathBuf::from(&new_image_name)
-                        };
-                        image.save_with_format(save_path, image::ImageFormat::Png)?;
-                        println!("    ✅ Saved resource image data to '{}'", save_path.as_path().to_string_lossy());
-
-                        if ARGS.open
-                        {
-                            println!("    Opening image...");
-                            opener::open(save_path.as_path())?;
-                        }
-                    },
-                    Err(error) => {println!("    ❌ Error converting resource {} to image: {}", res_num, error)},
+                    if let Err(error) = export_mips(&buffer, resource_entry_info, &vtfx, &decode_options, path, res_num) {
+                        println!("    ❌ Error converting resource {} to image: {}", res_num, error);
+                    }
                 }
-            }
-            
-            res_num += 1;
+
+                res_num += 1;
+            },
+            Resource::LowResImage { .. } if !ARGS.no_resource_export && ARGS.low_res != LowResExport::None => {
+                let (low_res_width, low_res_height) = vtfx.get_low_res_size();
+                println!("    Low res image: w: {low_res_width}, h: {low_res_height}");
+
+                match low_res_resource_to_image(&buffer, resource_entry_info, &vtfx, &decode_options) {
+                    Ok(image) => save_image(&image, path, "low_res")?,
+                    Err(error) => {println!("    ❌ Error converting low res resource to image: {}", error)},
+                }
+            },
+            Resource::Sheet(sheet) => {
+                for sequence in &sheet.sequences
+                {
+                    println!("        Sequence {}: {} frames, total time: {}, clamp: {}", sequence.sequence_number, sequence.frames.len(), sequence.total_time, sequence.clamp);
+                }
+            },
+            Resource::KeyValueData(text) => {
+                println!("{}", text);
+            },
+            Resource::Unknown { .. } => {
+                println!("    Unknown resource type, skipping...");
+            },
+            _ => {}
         }
-        else
-        {
-            println!("Error: Unknown resource type, skipping...");
+    }
+
+    if ARGS.vtf
+    {
+        let save_path = get_save_path(path, "pc", "vtf");
+        match vtf_writer::write_vtf(&buffer, &vtfx, &decode_options, ARGS.vtf_version, BufWriter::new(File::create(&save_path)?)) {
+            Ok(_) => println!("    ✅ Saved PC vtf (7.{}) to '{}'", ARGS.vtf_version, save_path.as_path().to_string_lossy()),
+            Err(error) => println!("    ❌ Error converting to PC vtf: {}", error),
         }
     }
 
     Ok(vtfx)
 }
 
-///Extract image resource and return it as DynamicImage
-fn resource_to_image(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, vtfx: &VTFXHEADER, res_num: &i32) -> Result<DynamicImage, Box<dyn Error>>
+///Export the mip levels of an image resource selected by the command line arguments
+fn export_mips(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, vtfx: &VTFXHEADER, options: &DecodeOptions, path: &Path, res_num: i32) -> Result<(), Box<dyn Error>>
 {
-    let res_start: usize = resource_entry_info.resData.try_into()?;
-    //Copy input buffer
-    let mut resource_buffer = Vec::from(&buffer[res_start..buffer.len()]);
-    let image_format = &vtfx.image_format;
-    let format_info = image_format.get_format_info();
-    if format_info.is_some()
+    let mips = resource_to_mips(buffer, resource_entry_info, vtfx, options)?;
+
+    let levels: Vec<usize> = if ARGS.all_mips
+    {
+        println!("    Exporting all {} mip levels", mips.mip_count());
+        (0..mips.mip_count()).collect()
+    }
+    else
     {
-        let format_info_u = format_info.unwrap();
+        vec![ARGS.mip.unwrap_or(0)]
+    };
 
-        if *image_format == ImageFormat::IMAGE_FORMAT_BGRA8888
-        {
-            println!("❕ NOTE: In this version ({}), BGRA8888 has its channel order changed to G,B,A,R. If this causes invalid results then please open a github issue.", VERSION)
-        }
+    if mips.is_tiled()
+    {
+        println!("    Resource {res_num} is xbox 360 tiled, untiling before decode...");
+    }
 
-        let mut image_vec: Vec<u8>;
+    if mips.is_swizzled()
+    {
+        println!("    Resource {res_num} is ps3 swizzled, deswizzling before decode...");
+    }
 
-        let width = vtfx.width as usize;
-        let height = vtfx.height as usize;
+    if mips.frame_count() > 1
+    {
+        println!("    Resource {res_num} has {} frames, each frame will be exported", mips.frame_count());
+    }
 
-        let mut bc_read_offset = 0;
+    if mips.face_count() > 1
+    {
+        println!("    Resource {res_num} is a cubemap with {} faces", mips.face_count());
+    }
 
-        println!("Resource #{res_num}: w: {width}, h: {height}");
+    if mips.palette().is_some()
+    {
+        println!("    Resource {res_num} is palettized, expanding with its palette...");
+    }
 
-        let is_lzma_compressed = &resource_buffer[0..4] == LZMA_MAGIC;
+    if vtfx.is_volume()
+    {
+        println!("    Resource {res_num} is a volume texture with {} slices", vtfx.get_depth());
+    }
 
-        //Is this resource lzma compressed?
-        let expected_size = format_info_u.get_expected_size(&width, &height);
-        if is_lzma_compressed
+    for level in levels
+    {
+        let name = match ARGS.all_mips || ARGS.mip.is_some()
         {
-            println!("    Image resource is LZMA compressed, decompressing...");
-            //Decompress and replace resource buffer
-            resource_buffer = decompress_lzma(&mut resource_buffer, expected_size, vtfx)?;
-        }
-        else if resource_buffer.len() < expected_size
+            true => format!("resource_{res_num}_mip_{level}"),
+            false => format!("resource_{res_num}")
+        };
+
+        let frames = mips.get_frames(level)?;
+        if let Some(mip) = frames.first().filter(|_| ARGS.all_mips || ARGS.mip.is_some())
         {
-            let size_error = io::Error::new(io::ErrorKind::InvalidInput, format!("resource size is {} but expected length is {}, resource cannot decoded", resource_buffer.len(), expected_size));
-            return Err(Box::new(size_error));
+            println!("    Mip {}: w: {}, h: {}", mip.level, mip.width, mip.height);
         }
 
-        //If this format is BC encoded
-        if format_info_u.bc_format.is_some()
+        for mip in &frames
         {
-            let bc_format = format_info_u.bc_format.unwrap();
-            //Allocate space for 4 channels
-            image_vec = vec![0; width * height * 4];
-            let image_vec_slice = image_vec.as_mut_slice();
+            let frame_name = match frames.len() > 1
+            {
+                true => format!("{name}_frame_{}", mip.frame),
+                false => name.clone()
+            };
 
-            if vtfx.is_xbox() || ARGS.force_dxt_endian_fix
+            if mips.face_count() > 1
             {
-                println!("    Applying endianness fix to resource '{res_num}' before dxt decode...");
-                correct_dxt_endianness(&bc_format, &mut resource_buffer)?;
+                export_cubemap(&mips, level, mip.frame, path, &frame_name)?;
             }
-            else
+            else if mips.slice_count(level) > 1
             {
-                println!("! Will skip applying dxt endian fix for image resource '{res_num}' !")
+                export_volume(&mips, level, mip.frame, path, &frame_name)?;
             }
-
-            if vtfx.mip_count > 1
+            else if ARGS.indexed && mips.palette().is_some()
             {
-                println!("    Resource {res_num} contains {} mip levels, only mip 0 will be exported", vtfx.mip_count);
-                bc_read_offset = resource_buffer.len() - vtfx.get_dxt_size()?;
-            }
-
-            println!("    Decoding image from {:?}, DTX buffer offset: {}", format_info_u.bc_format.unwrap(), bc_read_offset);
-            //Decompress dxt image, if its still compressed this will fail
-            //Use read offset when getting dtx buffer slice
-            bc_format.decompress(&mut resource_buffer[bc_read_offset..], width, height, image_vec_slice);
-        }
-        else
-        {
-            if vtfx.is_xbox() && vtfx.mip_count > 1
-            {
-                #[cfg(debug_assertions)]
-                {
-                    println!("    [DEBUG] Will adjust image vector to start at largest mip map");
-                }
-
-                //Make new vector but skip to start of largest mip map (as img resources are packed smallest to largest dimension wise)
-                let large_mip_start = resource_buffer.len() - expected_size;
-                image_vec = resource_buffer[large_mip_start..].to_vec();
+                save_indexed_image(&mips, mip, path, &frame_name)?;
             }
             else
             {
-                //Resource buffer is already usable
-                image_vec = resource_buffer;
+                save_image(&mip.image, path, &frame_name)?;
             }
         }
 
-        //Take decompressed data and put into image
-        let mut output_image = DynamicImage::new_rgba8(vtfx.width.into(), vtfx.height.into());
-
-        if ARGS.export_alpha
+        if frames.len() > 1 && ARGS.animation != AnimationExport::None
         {
-            println!("    Alpha will be included in the export for image resource '{res_num}'");
+            let images: Vec<DynamicImage> = frames.into_iter().map(|mip| mip.image).collect();
+            save_animation(&images, path, &name)?;
         }
+    }
 
-        let width_u32 = width as u32;
-        let depth_u32 = format_info_u.depth as u32;
-        let channels = format_info_u.channels as usize;
-        for y in 0..output_image.height()
-        {
-            for x in 0..output_image.width()
+    Ok(())
+}
+
+///Export the faces of a cubemap as selected by the command line arguments
+fn export_cubemap(mips: &MipIterator, level: usize, frame: usize, path: &Path, name: &str) -> Result<(), Box<dyn Error>>
+{
+    let faces = mips.get_faces(level, frame)?;
+    let face_images: Vec<DynamicImage> = faces.into_iter().map(|face| face.image).collect();
+    match ARGS.cubemap
+    {
+        CubemapExport::Faces => {
+            for (face, image) in face_images.iter().enumerate()
             {
-                let mut pixel: Rgba<u8> = Rgba([255;4]);
-                //Index of pixel data to read from decoded output
-                let pixel_index = (x + y * width_u32) * depth_u32 * 4;
-                for channel in 0..channels
-                {
-                    //Using format data, construct index and copy source image pixel colour data
-                    let channel_offset = format_info_u.channel_order[channel] as u32;
-                    //Add channel offset to pixel index.
-                    let from_index: usize = (pixel_index + (channel_offset * depth_u32)) as usize;
-
-                    if from_index < image_vec.len()
-                    {
-                        pixel[channel] = get_pixel_as_u8(&image_vec, from_index, &format_info_u.depth)?;
-                    }
-                }
-                
-                //Override alpha if not explicitly enabled
-                if !ARGS.export_alpha
-                {
-                    pixel[3] = 255;
-                }
-                else if ARGS.experimental_onebitalpha
-                {
-                    pixel[3] = match pixel[3] > 1{
-                        true => 0,
-                        false => 255
-                    };
-                }
-                
-                output_image.put_pixel(x, y, pixel);
+                let face_name = CubemapFace::try_from(face)?.get_name();
+                save_image(image, path, &format!("{name}_{face_name}"))?;
             }
+        },
+        CubemapExport::Cross => {
+            let cross = cubemap::faces_to_cross(&face_images)?;
+            save_image(&cross, path, &format!("{name}_cross"))?;
+        },
+        CubemapExport::Dds => {
+            let save_path = get_save_path(path, name, "dds");
+            cubemap::write_dds_cubemap(&face_images, BufWriter::new(File::create(&save_path)?))?;
+            println!("    ✅ Saved cubemap to '{}'", save_path.as_path().to_string_lossy());
         }
-
-        Ok(output_image)
     }
-    else
+
+    Ok(())
+}
+
+///Export the slices of a volume texture as selected by the command line arguments
+fn export_volume(mips: &MipIterator, level: usize, frame: usize, path: &Path, name: &str) -> Result<(), Box<dyn Error>>
+{
+    let slices = mips.get_slices(level, frame)?;
+    let slice_images: Vec<DynamicImage> = slices.into_iter().map(|slice| slice.image).collect();
+    match ARGS.volume
     {
-        let err = io::Error::new(io::ErrorKind::Other, format!("Unsupported image format: {:?}.\nRequest for other formats to be supported on github.", image_format));
-        return Err(Box::new(err));
+        VolumeExport::Strip => {
+            let strip = volume::slices_to_strip(&slice_images)?;
+            save_image(&strip, path, &format!("{name}_slices"))?;
+        },
+        VolumeExport::Slices => {
+            for (slice, image) in slice_images.iter().enumerate()
+            {
+                save_image(image, path, &format!("{name}_slice_{slice}"))?;
+            }
+        }
     }
+
+    Ok(())
 }
 
-///Decompress resource that is compressed via lzma. Creates new buffer with new header.
-fn decompress_lzma(resource_buffer: &mut Vec<u8>, expected_compressed_size: usize, vtfx: &VTFXHEADER) -> Result<Vec<u8>, Box<dyn Error>>
+///Save the palette indices of a P8 image as an indexed png
+fn save_indexed_image(mips: &MipIterator, mip: &MipLevel, path: &Path, name: &str) -> Result<(), Box<dyn Error>>
 {
-    //Read data from valves lzma header
-    let actual_size: u64 = u32::from_le_bytes(resource_buffer[4..8].try_into()?).into();
-    let mut decomp: Vec<u8> = Vec::with_capacity(actual_size as usize);
-    let compressed_size: u32 = u32::from_le_bytes(resource_buffer[8..12].try_into()?);
-    let mut dictionary_size: u32 = 0;
-    let props_data = &resource_buffer[12..16];
-    for i in 0..3
-    {
-        dictionary_size += (props_data[1 + i] as u32) << (i * 8);
-    }
-    if dictionary_size == 0
+    let Some(palette) = mips.palette() else {
+        return Ok(());
+    };
+
+    let indices = mips.get_mip_indices(mip.level, mip.frame, mip.face, mip.slice)?;
+    let save_path = get_save_path(path, name, "png");
+    palette::write_indexed_png(&indices, mip.width, mip.height, palette, ARGS.export_alpha, BufWriter::new(File::create(&save_path)?))?;
+    println!("    ✅ Saved indexed image to '{}'", save_path.as_path().to_string_lossy());
+
+    Ok(())
+}
+
+///Save frames as an animation next to the input file (or in the output folder)
+fn save_animation(frames: &[DynamicImage], path: &Path, suffix: &str) -> Result<(), Box<dyn Error>>
+{
+    let extension = match ARGS.animation
+    {
+        AnimationExport::Apng => "png",
+        _ => "gif"
+    };
+    let save_path = get_save_path(path, &format!("{suffix}_animated"), extension);
+    let writer = BufWriter::new(File::create(&save_path)?);
+    match ARGS.animation
+    {
+        AnimationExport::Apng => animation::write_apng(frames, ARGS.frame_rate, writer)?,
+        _ => animation::write_gif(frames, ARGS.frame_rate, writer)?
+    };
+    println!("    ✅ Saved animation to '{}'", save_path.as_path().to_string_lossy());
+
+    Ok(())
+}
+
+///Get path to save an export to, next to the input file (or in the output folder) with suffix added to its name
+fn get_save_path(path: &Path, suffix: &str, extension: &str) -> PathBuf
+{
+    let filename = path.file_stem().unwrap().to_str().unwrap();
+    let new_name = format!("{filename}_{suffix}.{extension}");
+    match ARGS.output.as_ref()
     {
-        dictionary_size = 1;
+        Some(output) => output.join(&new_name),
+        None => PathBuf::from(&new_name)
     }
+}
 
-    //Reconstruct new header + data to decompress
-    let mut new_header_resource_buffer: Vec<u8> = Vec::with_capacity(resource_buffer.len());
-    new_header_resource_buffer.push(resource_buffer[12]);
-
-    //Print valves properties if debug build
-    #[cfg(debug_assertions)]
-    {
-        println!("[Debug LZMA] Dictionary size: {}", dictionary_size);
-        let mut prop0: u8 = u8::from(resource_buffer[12]);
-        let original_prop0 = prop0.clone();
-        let mut pb: i32 = 0;
-        let mut lp: i32 = 0;
-        let mut lc: i32 = 0;
-        get_valve_lzma_properties(&mut prop0, &mut pb, &mut lp, &mut lc);
-        println!("[Debug LZMA] Properties Decoded ({}): prop0: {}, pb: {}, lp: {}, lc: {}", original_prop0, prop0, pb, lp, lc);
+///Save image as png next to the input file (or in the output folder) with suffix added to its name.
+///Float images are saved as exr or hdr instead.
+fn save_image(image: &DynamicImage, path: &Path, suffix: &str) -> Result<(), Box<dyn Error>>
+{
+    if let DynamicImage::ImageRgba32F(_) = image
+    {
+        return save_hdr_image(image, path, suffix);
     }
 
-    new_header_resource_buffer.extend_from_slice(&dictionary_size.to_le_bytes());
-    new_header_resource_buffer.extend_from_slice(&actual_size.to_le_bytes());
-    new_header_resource_buffer.extend_from_slice(&resource_buffer[17..]);
-    *resource_buffer = new_header_resource_buffer;
+    let save_path = &get_save_path(path, suffix, "png");
+    image.save_with_format(save_path, image::ImageFormat::Png)?;
+    println!("    ✅ Saved resource image data to '{}'", save_path.as_path().to_string_lossy());
 
-    if cfg!(debug_assertions) {
-        println!("[Debug LZMA] Actual size: {} (Expected {}). Compressed size: {} (header cmpr size {})", actual_size, expected_compressed_size, compressed_size, vtfx.compressed_size);
+    if ARGS.open
+    {
+        println!("    Opening image...");
+        opener::open(save_path.as_path())?;
     }
 
-    lzma_rs::lzma_decompress(&mut &resource_buffer[..], &mut decomp)?;
-    println!("    Decompressed to: {}, Expected: {}", decomp.len(), expected_compressed_size);
-    Ok(decomp)
+    Ok(())
 }
 
-///Get pixel as u8. Convert larger sized pixels down
-fn get_pixel_as_u8(in_buffer: &Vec<u8>, index: usize, depth: &u16) -> Result<u8, Box<dyn Error>>
+///Save float image as exr or hdr, and a tonemapped png preview if enabled
+fn save_hdr_image(image: &DynamicImage, path: &Path, suffix: &str) -> Result<(), Box<dyn Error>>
 {
-    match depth
+    let save_path = match ARGS.hdr
     {
-        1 => Ok(in_buffer[index]),
-        2 => {
-            let colour = u16::from_be_bytes(in_buffer[index..index+2].try_into()?);
-            Ok((colour / 2).try_into()?)
-        },
-        4 => {
-            let colour = u32::from_be_bytes(in_buffer[index..index+4].try_into()?);
-            Ok((colour / 4).try_into()?)
+        HdrExport::Exr => {
+            let save_path = get_save_path(path, suffix, "exr");
+            hdr::write_exr(image, BufWriter::new(File::create(&save_path)?))?;
+            save_path
         },
-        _ => Err(Box::new(io::Error::new(io::ErrorKind::Other, format!("Unexpected depth size '{}'", depth))))
+        HdrExport::Hdr => {
+            let save_path = get_save_path(path, suffix, "hdr");
+            hdr::write_hdr(image, BufWriter::new(File::create(&save_path)?))?;
+            save_path
+        }
+    };
+    println!("    ✅ Saved resource image data to '{}'", save_path.as_path().to_string_lossy());
+
+    if ARGS.tonemap
+    {
+        save_image(&hdr::tonemap(image, ARGS.exposure), path, &format!("{suffix}_tonemapped"))?;
     }
+
+    Ok(())
 }
 
-/// Get lzma properties same way as source 2013
-fn get_valve_lzma_properties(prop0: &mut u8, pb: &mut i32, lp: &mut i32, lc: &mut i32) 
+///Print what will happen when decoding an image resource
+fn print_image_resource_info(buffer: &[u8], res_start: usize, vtfx: &VTFXHEADER, options: &DecodeOptions, res_num: i32)
 {
-    while pb < &mut 5 && prop0 >= & mut(9 * 5){
-        *pb += 1;
-        *prop0 -= 45;
+    let image_format = &vtfx.image_format;
+    let Some(format_info) = image_format.get_format_info() else {
+        return;
+    };
+
+    println!("Resource #{res_num}: w: {}, h: {}", vtfx.width, vtfx.height);
+
+    if buffer.len() > res_start && is_lzma_compressed(&buffer[res_start..])
+    {
+        println!("    Image resource is LZMA compressed, decompressing...");
     }
-    
-    // Second loop:
-    while lp < &mut 5 && prop0 >= &mut 9 {
-        *lp += 1;
-        *prop0 -= 9;
+
+    if format_info.bc_format.is_some()
+    {
+        if vtfx.is_xbox() || options.force_dxt_endian_fix
+        {
+            println!("    Applying endianness fix to resource '{res_num}' before dxt decode...");
+        }
+        else
+        {
+            println!("! Will skip applying dxt endian fix for image resource '{res_num}' !")
+        }
+
+        if vtfx.mip_count > 1 && !ARGS.all_mips && ARGS.mip.is_none()
+        {
+            println!("    Resource {res_num} contains {} mip levels, only mip 0 will be exported", vtfx.mip_count);
+        }
     }
 
-    *lc = *prop0 as i32;
+    if options.export_alpha
+    {
+        println!("    Alpha will be included in the export for image resource '{res_num}'");
+    }
 }
@@ -0,0 +1,136 @@
+use std::io::Write;
+
+use crate::error::VtfxError;
+
+///Number of colours in a P8 palette
+pub const PALETTE_SIZE: usize = 256;
+///Size of a palette of RGB colours (also the size of a photoshop .act file)
+const RGB_PALETTE_LENGTH: usize = PALETTE_SIZE * 3;
+///.act files may have 4 extra bytes, the colour count and transparent index (big endian u16's)
+const ACT_PALETTE_LENGTH: usize = RGB_PALETTE_LENGTH + 4;
+const RGBA_PALETTE_LENGTH: usize = PALETTE_SIZE * 4;
+
+///RGBA colours of a P8 (palettized) image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette
+{
+    pub colours: [[u8; 4]; PALETTE_SIZE]
+}
+
+impl Palette
+{
+    ///Read palette from 256 RGB (768 bytes, or a 772 byte .act file) or RGBA (1024 bytes) colours
+    pub fn from_bytes(data: &[u8]) -> Result<Palette, VtfxError>
+    {
+        let channels = match data.len()
+        {
+            RGB_PALETTE_LENGTH | ACT_PALETTE_LENGTH => 3,
+            RGBA_PALETTE_LENGTH => 4,
+            length => return Err(VtfxError::InvalidPalette { length })
+        };
+
+        let mut colours = [[0, 0, 0, 255]; PALETTE_SIZE];
+        for (colour, bytes) in colours.iter_mut().zip(data.chunks_exact(channels))
+        {
+            colour[..channels].copy_from_slice(bytes);
+        }
+
+        if data.len() == ACT_PALETTE_LENGTH
+        {
+            let transparent_index = u16::from_be_bytes([data[RGB_PALETTE_LENGTH + 2], data[RGB_PALETTE_LENGTH + 3]]) as usize;
+            if let Some(colour) = colours.get_mut(transparent_index)
+            {
+                colour[3] = 0;
+            }
+        }
+
+        Ok(Palette { colours })
+    }
+
+    ///Expand palette indices to 8 bit RGBA
+    pub fn expand(&self, indices: &[u8]) -> Vec<u8>
+    {
+        indices.iter().flat_map(|index| self.colours[*index as usize]).collect()
+    }
+}
+
+///Encode palette indices as an indexed png with the palette. Alpha is written as a tRNS chunk if enabled.
+pub fn write_indexed_png<W: Write>(indices: &[u8], width: usize, height: usize, palette: &Palette, alpha: bool, writer: W) -> Result<(), VtfxError>
+{
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.colours.iter().flat_map(|colour| [colour[0], colour[1], colour[2]]).collect::<Vec<u8>>());
+    if alpha
+    {
+        encoder.set_trns(palette.colours.iter().map(|colour| colour[3]).collect::<Vec<u8>>());
+    }
+
+    let mut png_writer = encoder.write_header()?;
+    png_writer.write_image_data(indices)?;
+    png_writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    ///256 RGB colours where index i is (i, 255 - i, i / 2)
+    fn rgb_palette() -> Vec<u8>
+    {
+        (0..=255u8).flat_map(|index| [index, 255 - index, index / 2]).collect()
+    }
+
+    #[test]
+    fn rgb_palette_is_opaque()
+    {
+        let palette = Palette::from_bytes(&rgb_palette()).unwrap();
+        assert_eq!(palette.colours[0], [0, 255, 0, 255]);
+        assert_eq!(palette.colours[255], [255, 0, 127, 255]);
+        assert!(palette.colours.iter().all(|colour| colour[3] == 255));
+    }
+
+    #[test]
+    fn act_palette_has_transparent_index()
+    {
+        //256 colours, colour 3 is transparent
+        let mut data = rgb_palette();
+        data.extend_from_slice(&[0x01, 0x00, 0x00, 0x03]);
+        let palette = Palette::from_bytes(&data).unwrap();
+        assert_eq!(palette.colours[3], [3, 252, 1, 0]);
+        assert_eq!(palette.colours.iter().filter(|colour| colour[3] == 0).count(), 1);
+
+        //No transparent colour is 0xFFFF
+        data[RGB_PALETTE_LENGTH + 2..].copy_from_slice(&[0xFF, 0xFF]);
+        let palette = Palette::from_bytes(&data).unwrap();
+        assert!(palette.colours.iter().all(|colour| colour[3] == 255));
+    }
+
+    #[test]
+    fn rgba_palette_keeps_alpha()
+    {
+        let data: Vec<u8> = (0..=255u8).flat_map(|index| [index, 0, 0, 255 - index]).collect();
+        let palette = Palette::from_bytes(&data).unwrap();
+        assert_eq!(palette.colours[1], [1, 0, 0, 254]);
+        assert_eq!(palette.colours[255], [255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn other_lengths_are_rejected()
+    {
+        for length in [0, 767, 769, 1023, 1025]
+        {
+            assert!(matches!(Palette::from_bytes(&vec![0; length]), Err(VtfxError::InvalidPalette { length: actual }) if actual == length), "length {}", length);
+        }
+    }
+
+    #[test]
+    fn indices_are_expanded_to_rgba()
+    {
+        let palette = Palette::from_bytes(&rgb_palette()).unwrap();
+        assert_eq!(palette.expand(&[2, 0, 255]), [2, 253, 1, 255, 0, 255, 0, 255, 255, 0, 127, 255]);
+    }
+}
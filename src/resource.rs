@@ -0,0 +1,335 @@
+use std::fmt;
+
+use crate::error::VtfxError;
+use crate::resource_entry_info::{ResourceEntryInfo, ResourceType};
+
+///A parsed vtfx resource
+#[derive(Debug)]
+pub enum Resource
+{
+    ///High res image data at offset. Use [`crate::resource_to_image`] to decode
+    Image { offset: usize },
+    ///Low res (thumbnail) image data at offset
+    LowResImage { offset: usize },
+    ///Animated sprite sheet sequences
+    Sheet(Sheet),
+    ///CRC of the original texture data
+    Crc(u32),
+    ///Resolution clamp settings
+    LodSettings(TextureLODControlSettings),
+    ///Extended texture settings flags
+    SettingsEx([u8; 4]),
+    ///Key value text data
+    KeyValueData(String),
+    ///Resource of unknown type, data is the raw resData value
+    Unknown { type_bytes: [u8; 4], data: u32 },
+}
+
+//https://github.com/ValveSoftware/source-sdk-2013/blob/master/sp/src/public/vtf/vtf.h
+#[derive(Debug, Default, Copy, Clone)]
+pub struct TextureLODControlSettings
+{
+    pub resolution_clamp_x: u8,
+    pub resolution_clamp_y: u8,
+    pub resolution_clamp_x_360: u8,
+    pub resolution_clamp_y_360: u8,
+}
+
+///Sprite sheet data as read by CSheet
+#[derive(Debug, Default, Clone)]
+pub struct Sheet
+{
+    pub version: i32,
+    pub sequences: Vec<SheetSequence>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SheetSequence
+{
+    pub sequence_number: i32,
+    pub clamp: bool,
+    pub total_time: f32,
+    pub frames: Vec<SheetFrame>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SheetFrame
+{
+    pub duration: f32,
+    ///Texture coordinates (u0, v0, u1, v1) for each image in this frame
+    pub coords: Vec<[f32; 4]>,
+}
+
+impl Resource
+{
+    ///Parse resource from its entry in the resource table
+    pub fn parse(buffer: &[u8], resource_entry_info: &ResourceEntryInfo, res_num: usize) -> Result<Resource, VtfxError>
+    {
+        let offset = resource_entry_info.resData as usize;
+        let resource = match resource_entry_info.get_type()
+        {
+            ResourceType::VTF_LEGACY_RSRC_IMAGE => Resource::Image { offset },
+            ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE => Resource::LowResImage { offset },
+            ResourceType::VTF_RSRC_TEXTURE_CRC => Resource::Crc(resource_entry_info.resData),
+            ResourceType::VTF_RSRC_TEXTURE_LOD_SETTINGS => {
+                //Settings struct is stored in resData, which was byte swapped as a whole u32
+                let bytes = resource_entry_info.resData.to_le_bytes();
+                Resource::LodSettings(TextureLODControlSettings {
+                    resolution_clamp_x: bytes[0],
+                    resolution_clamp_y: bytes[1],
+                    resolution_clamp_x_360: bytes[2],
+                    resolution_clamp_y_360: bytes[3],
+                })
+            },
+            ResourceType::VTF_RSRC_TEXTURE_SETTINGS_EX => Resource::SettingsEx(resource_entry_info.resData.to_le_bytes()),
+            ResourceType::VTF_RSRC_SHEET => {
+                let mut reader = ResourceReader::new(buffer, offset, res_num)?;
+                Resource::Sheet(Sheet::read(&mut reader)?)
+            },
+            ResourceType::VTF_RSRC_KEY_VALUE_DATA => {
+                let mut reader = ResourceReader::new(buffer, offset, res_num)?;
+                let length = reader.remaining();
+                let data = reader.read_slice(length)?;
+                Resource::KeyValueData(String::from_utf8_lossy(data).trim_end_matches('\0').to_string())
+            },
+            ResourceType::Unknown(_) => Resource::Unknown { type_bytes: resource_entry_info.chTypeBytes, data: resource_entry_info.resData },
+        };
+
+        Ok(resource)
+    }
+}
+
+impl Sheet
+{
+    fn read(reader: &mut ResourceReader) -> Result<Sheet, VtfxError>
+    {
+        let version = reader.read_i32()?;
+        let num_coords_per_frame = match version {
+            0 => 1,
+            _ => 4
+        };
+
+        let num_sequences = reader.read_i32()?;
+        let mut sequences = Vec::new();
+        for _ in 0..num_sequences
+        {
+            let mut sequence = SheetSequence {
+                sequence_number: reader.read_i32()?,
+                clamp: reader.read_i32()? != 0,
+                ..Default::default()
+            };
+            let num_frames = reader.read_i32()?;
+            sequence.total_time = reader.read_f32()?;
+
+            for _ in 0..num_frames
+            {
+                let mut frame = SheetFrame { duration: reader.read_f32()?, coords: Vec::new() };
+                for _ in 0..num_coords_per_frame
+                {
+                    frame.coords.push([reader.read_f32()?, reader.read_f32()?, reader.read_f32()?, reader.read_f32()?]);
+                }
+                sequence.frames.push(frame);
+            }
+
+            sequences.push(sequence);
+        }
+
+        Ok(Sheet { version, sequences })
+    }
+}
+
+///Reads big endian values from a resource data chunk (u32 length followed by data)
+struct ResourceReader<'a>
+{
+    data: &'a [u8],
+    start: usize,
+    position: usize,
+    resource: usize,
+}
+
+impl<'a> ResourceReader<'a>
+{
+    fn new(buffer: &'a [u8], offset: usize, resource: usize) -> Result<ResourceReader<'a>, VtfxError>
+    {
+        let mut length_reader = ResourceReader { data: buffer, start: 0, position: offset, resource };
+        let length = u32::from_be_bytes(length_reader.read()?) as usize;
+        let data_start = offset + 4;
+        let data = buffer.get(data_start..data_start.saturating_add(length))
+            .ok_or(VtfxError::TruncatedResource { resource, offset: data_start, needed: length })?;
+
+        Ok(ResourceReader { data, start: data_start, position: 0, resource })
+    }
+
+    fn remaining(&self) -> usize
+    {
+        self.data.len() - self.position
+    }
+
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], VtfxError>
+    {
+        let slice = self.data.get(self.position..self.position.saturating_add(length))
+            .ok_or(VtfxError::TruncatedResource { resource: self.resource, offset: self.start + self.position, needed: length })?;
+        self.position += length;
+        Ok(slice)
+    }
+
+    fn read<const N: usize>(&mut self) -> Result<[u8; N], VtfxError>
+    {
+        let slice = self.read_slice(N)?;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(slice);
+        Ok(bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, VtfxError>
+    {
+        Ok(i32::from_be_bytes(self.read()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32, VtfxError>
+    {
+        Ok(f32::from_be_bytes(self.read()?))
+    }
+}
+
+impl fmt::Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self
+        {
+            Resource::Image { offset } => write!(f, "Image (offset: {})", offset),
+            Resource::LowResImage { offset } => write!(f, "Low res image (offset: {})", offset),
+            Resource::Sheet(sheet) => write!(f, "Sheet (version: {}, sequences: {})", sheet.version, sheet.sequences.len()),
+            Resource::Crc(crc) => write!(f, "CRC ({:#010x})", crc),
+            Resource::LodSettings(lod) => write!(f, "LOD settings (clamp: {}x{}, 360 clamp: {}x{})", lod.resolution_clamp_x, lod.resolution_clamp_y, lod.resolution_clamp_x_360, lod.resolution_clamp_y_360),
+            Resource::SettingsEx(flags) => write!(f, "Texture settings ex (flags: {:?})", flags),
+            Resource::KeyValueData(text) => write!(f, "Key value data ({} chars)", text.len()),
+            Resource::Unknown { type_bytes, data } => write!(f, "Unknown (type: {:?}, data: {})", type_bytes, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::vtfx::{VTF_RSRC_KEY_VALUE_DATA, VTF_RSRC_SHEET, VTF_RSRC_TEXTURE_CRC, VTF_RSRC_TEXTURE_LOD_SETTINGS, VTF_RSRC_TEXTURE_SETTINGS_EX};
+
+    fn entry(type_bytes: [u8; 4], res_data: u32) -> ResourceEntryInfo
+    {
+        ResourceEntryInfo { chTypeBytes: type_bytes, resData: res_data }
+    }
+
+    ///Data chunk of a big endian u32 length followed by data
+    fn chunk(data: &[u8]) -> Vec<u8>
+    {
+        let mut buffer = (data.len() as u32).to_be_bytes().to_vec();
+        buffer.extend_from_slice(data);
+        buffer
+    }
+
+    fn be_bytes(values: &[f32]) -> Vec<u8>
+    {
+        values.iter().flat_map(|value| value.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn sheet_is_parsed()
+    {
+        //Version 1 sheet, 1 sequence (number 2, clamped) with 2 frames of 4 coords each
+        let mut data = Vec::new();
+        for value in [1i32, 1, 2, 1, 2]
+        {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend(be_bytes(&[0.5]));
+        for frame in 0..2
+        {
+            data.extend(be_bytes(&[0.25]));
+            for image in 0..4
+            {
+                let u = (frame * 4 + image) as f32 / 8.0;
+                data.extend(be_bytes(&[u, 0.0, u + 0.125, 1.0]));
+            }
+        }
+
+        let mut buffer = vec![0; 8];
+        buffer.extend(chunk(&data));
+        let Resource::Sheet(sheet) = Resource::parse(&buffer, &entry(VTF_RSRC_SHEET, 8), 0).unwrap() else {
+            panic!("expected sheet");
+        };
+
+        assert_eq!(sheet.version, 1);
+        assert_eq!(sheet.sequences.len(), 1);
+        let sequence = &sheet.sequences[0];
+        assert_eq!((sequence.sequence_number, sequence.clamp, sequence.total_time), (2, true, 0.5));
+        assert_eq!(sequence.frames.len(), 2);
+        assert_eq!(sequence.frames[1].duration, 0.25);
+        assert_eq!(sequence.frames[1].coords.len(), 4);
+        assert_eq!(sequence.frames[1].coords[3], [0.875, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn version_0_sheet_has_one_coord_per_frame()
+    {
+        let mut data = Vec::new();
+        for value in [0i32, 1, 0, 0, 1]
+        {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend(be_bytes(&[1.0, 1.0, 0.0, 0.0, 1.0, 1.0]));
+
+        let Resource::Sheet(sheet) = Resource::parse(&chunk(&data), &entry(VTF_RSRC_SHEET, 0), 0).unwrap() else {
+            panic!("expected sheet");
+        };
+        assert_eq!(sheet.sequences[0].frames[0].coords, vec![[0.0, 0.0, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn truncated_sheet_is_rejected()
+    {
+        //Sheet claims 1 sequence but the chunk ends after the sequence count
+        let mut data = 1i32.to_be_bytes().to_vec();
+        data.extend_from_slice(&1i32.to_be_bytes());
+        let result = Resource::parse(&chunk(&data), &entry(VTF_RSRC_SHEET, 0), 3);
+        assert!(matches!(result, Err(VtfxError::TruncatedResource { resource: 3, offset: 12, needed: 4 })), "{:?}", result);
+    }
+
+    #[test]
+    fn chunk_longer_than_file_is_rejected()
+    {
+        let mut buffer = chunk(b"key value");
+        buffer.truncate(8);
+        let result = Resource::parse(&buffer, &entry(VTF_RSRC_KEY_VALUE_DATA, 0), 1);
+        assert!(matches!(result, Err(VtfxError::TruncatedResource { resource: 1, offset: 4, needed: 9 })), "{:?}", result);
+    }
+
+    #[test]
+    fn key_value_data_is_parsed()
+    {
+        let buffer = chunk(b"\"texture\" { }\0\0");
+        let Resource::KeyValueData(text) = Resource::parse(&buffer, &entry(VTF_RSRC_KEY_VALUE_DATA, 0), 0).unwrap() else {
+            panic!("expected key value data");
+        };
+        assert_eq!(text, "\"texture\" { }");
+    }
+
+    #[test]
+    fn value_resources_are_parsed()
+    {
+        assert!(matches!(Resource::parse(&[], &entry(VTF_RSRC_TEXTURE_CRC, 0x12345678), 0), Ok(Resource::Crc(0x12345678))));
+
+        let Ok(Resource::LodSettings(lod)) = Resource::parse(&[], &entry(VTF_RSRC_TEXTURE_LOD_SETTINGS, 0x04030201), 0) else {
+            panic!("expected lod settings");
+        };
+        assert_eq!((lod.resolution_clamp_x, lod.resolution_clamp_y, lod.resolution_clamp_x_360, lod.resolution_clamp_y_360), (1, 2, 3, 4));
+
+        assert!(matches!(Resource::parse(&[], &entry(VTF_RSRC_TEXTURE_SETTINGS_EX, 0x00000001), 0), Ok(Resource::SettingsEx([1, 0, 0, 0]))));
+    }
+
+    #[test]
+    fn unknown_resource_keeps_raw_value()
+    {
+        let result = Resource::parse(&[], &entry(*b"ABC\0", 42), 0);
+        assert!(matches!(result, Ok(Resource::Unknown { type_bytes, data: 42 }) if &type_bytes == b"ABC\0"), "{:?}", result);
+    }
+}
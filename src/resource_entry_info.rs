@@ -1,8 +1,58 @@
+use crate::vtfx::{RSRCF_HAS_NO_DATA_CHUNK, VTF_LEGACY_RSRC_IMAGE, VTF_LEGACY_RSRC_LOW_RES_IMAGE, VTF_RSRC_SHEET, VTF_RSRC_TEXTURE_CRC, VTF_RSRC_TEXTURE_LOD_SETTINGS, VTF_RSRC_TEXTURE_SETTINGS_EX, VTF_RSRC_KEY_VALUE_DATA};
+
 #[repr(C)]
 #[derive(Debug, Default)]
 #[allow(non_snake_case)] 
 pub struct ResourceEntryInfo
 {
-	pub chTypeBytes: [u8; 4],
+	pub chTypeBytes: [u8; 4],	// Resource type id (3 bytes) followed by flags byte
 	pub resData: u32	// Resource data or offset from the beginning of the file
-}
\ No newline at end of file
+}
+
+///Known resource types. Matched using the 3 id bytes of chTypeBytes (flags are ignored)
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[allow(non_camel_case_types)] //Keep names same as source
+pub enum ResourceType
+{
+	VTF_LEGACY_RSRC_LOW_RES_IMAGE,
+	VTF_LEGACY_RSRC_IMAGE,
+	VTF_RSRC_SHEET,
+	VTF_RSRC_TEXTURE_CRC,
+	VTF_RSRC_TEXTURE_LOD_SETTINGS,
+	VTF_RSRC_TEXTURE_SETTINGS_EX,
+	VTF_RSRC_KEY_VALUE_DATA,
+	Unknown([u8; 3])
+}
+
+const RESOURCE_TYPES: [([u8; 4], ResourceType); 7] = [
+	(VTF_LEGACY_RSRC_LOW_RES_IMAGE, ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE),
+	(VTF_LEGACY_RSRC_IMAGE, ResourceType::VTF_LEGACY_RSRC_IMAGE),
+	(VTF_RSRC_SHEET, ResourceType::VTF_RSRC_SHEET),
+	(VTF_RSRC_TEXTURE_CRC, ResourceType::VTF_RSRC_TEXTURE_CRC),
+	(VTF_RSRC_TEXTURE_LOD_SETTINGS, ResourceType::VTF_RSRC_TEXTURE_LOD_SETTINGS),
+	(VTF_RSRC_TEXTURE_SETTINGS_EX, ResourceType::VTF_RSRC_TEXTURE_SETTINGS_EX),
+	(VTF_RSRC_KEY_VALUE_DATA, ResourceType::VTF_RSRC_KEY_VALUE_DATA),
+];
+
+impl ResourceEntryInfo
+{
+	pub fn get_type(&self) -> ResourceType
+	{
+		RESOURCE_TYPES.iter()
+			.find(|(type_bytes, _)| type_bytes[..3] == self.chTypeBytes[..3])
+			.map(|(_, resource_type)| *resource_type)
+			.unwrap_or(ResourceType::Unknown([self.chTypeBytes[0], self.chTypeBytes[1], self.chTypeBytes[2]]))
+	}
+
+	///Flags byte of the resource type
+	pub fn get_flags(&self) -> u8
+	{
+		self.chTypeBytes[3]
+	}
+
+	///If false, resData holds the resource value instead of an offset to its data
+	pub fn has_data_chunk(&self) -> bool
+	{
+		(self.get_flags() & RSRCF_HAS_NO_DATA_CHUNK) == 0
+	}
+}
@@ -0,0 +1,134 @@
+//PS3 RSX swizzled texture layout (morton/z-order), x and y bits are interleaved starting with x
+//https://github.com/RPCS3/rpcs3/blob/master/rpcs3/Emu/RSX/Common/TextureUtils.cpp
+
+///Get offset (in pixels) of pixel x, y in a swizzled surface. Width and height must be powers of 2
+pub fn get_swizzled_offset(x: usize, y: usize, width: usize, height: usize) -> usize
+{
+    let log_width = width.trailing_zeros();
+    let log_height = height.trailing_zeros();
+    let interleaved_bits = log_width.min(log_height);
+
+    let mut offset = 0;
+    for bit in 0..interleaved_bits
+    {
+        offset |= ((x >> bit) & 1) << (bit * 2);
+        offset |= ((y >> bit) & 1) << (bit * 2 + 1);
+    }
+
+    //Remaining bits of the larger dimension follow the interleaved bits
+    match log_width > log_height
+    {
+        true => offset | ((x >> interleaved_bits) << (interleaved_bits * 2)),
+        false => offset | ((y >> interleaved_bits) << (interleaved_bits * 2))
+    }
+}
+
+///If a surface of this size can be swizzled (both dimensions are powers of 2)
+pub fn can_swizzle(width: usize, height: usize) -> bool
+{
+    width.is_power_of_two() && height.is_power_of_two()
+}
+
+///Reorder swizzled ps3 surface data into linear order. pixel_size is the size of a pixel in bytes.
+///Surfaces that are not a power of 2 in size are never swizzled and are returned as is.
+pub fn deswizzle_ps3(data: &[u8], width: usize, height: usize, pixel_size: usize) -> Vec<u8>
+{
+    if !can_swizzle(width, height)
+    {
+        return data.to_vec();
+    }
+
+    let mut linear = vec![0u8; width * height * pixel_size];
+    for y in 0..height
+    {
+        for x in 0..width
+        {
+            let from = get_swizzled_offset(x, y, width, height) * pixel_size;
+            let to = (x + y * width) * pixel_size;
+            if let Some(pixel) = data.get(from..from + pixel_size)
+            {
+                linear[to..to + pixel_size].copy_from_slice(pixel);
+            }
+        }
+    }
+
+    linear
+}
+
+///Reorder linear data into a swizzled ps3 surface (the inverse of [`deswizzle_ps3`]).
+///Surfaces that are not a power of 2 in size are never swizzled and are returned as is.
+pub fn swizzle_ps3(linear: &[u8], width: usize, height: usize, pixel_size: usize) -> Vec<u8>
+{
+    if !can_swizzle(width, height)
+    {
+        return linear.to_vec();
+    }
+
+    let mut swizzled = vec![0u8; width * height * pixel_size];
+    for y in 0..height
+    {
+        for x in 0..width
+        {
+            let to = get_swizzled_offset(x, y, width, height) * pixel_size;
+            let from = (x + y * width) * pixel_size;
+            if let Some(pixel) = linear.get(from..from + pixel_size)
+            {
+                swizzled[to..to + pixel_size].copy_from_slice(pixel);
+            }
+        }
+    }
+
+    swizzled
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn swizzled_offsets_interleave_x_and_y()
+    {
+        //4x4: x bits are even bits and y bits are odd bits of the offset
+        let offsets: Vec<usize> = [(0, 0), (1, 0), (0, 1), (1, 1), (2, 0), (0, 2), (3, 3)].iter().map(|&(x, y)| get_swizzled_offset(x, y, 4, 4)).collect();
+        assert_eq!(offsets, [0, 1, 2, 3, 4, 8, 15]);
+
+        //8x2: only 1 bit of each is interleaved, the rest of x follows
+        let offsets: Vec<usize> = [(1, 0), (0, 1), (2, 0), (7, 1)].iter().map(|&(x, y)| get_swizzled_offset(x, y, 8, 2)).collect();
+        assert_eq!(offsets, [1, 2, 4, 15]);
+
+        //2x8: the rest of y follows
+        let offsets: Vec<usize> = [(1, 0), (0, 1), (0, 2), (1, 7)].iter().map(|&(x, y)| get_swizzled_offset(x, y, 2, 8)).collect();
+        assert_eq!(offsets, [1, 2, 4, 15]);
+    }
+
+    #[test]
+    fn deswizzle_reorders_known_pair()
+    {
+        //4x4 single byte pixels, swizzled order of each linear index
+        let swizzled = [0, 1, 4, 5, 2, 3, 6, 7, 8, 9, 12, 13, 10, 11, 14, 15];
+        let linear: Vec<u8> = (0..16).collect();
+        assert_eq!(deswizzle_ps3(&swizzled, 4, 4, 1), linear);
+        assert_eq!(swizzle_ps3(&linear, 4, 4, 1), swizzled);
+    }
+
+    #[test]
+    fn swizzle_and_deswizzle_round_trip()
+    {
+        for (width, height, pixel_size) in [(16, 16, 4), (32, 4, 2), (2, 64, 1), (1, 8, 4), (8, 1, 3)]
+        {
+            let linear: Vec<u8> = (0..width * height * pixel_size).map(|i| (i * 7 % 251) as u8).collect();
+            let swizzled = swizzle_ps3(&linear, width, height, pixel_size);
+            assert_eq!(deswizzle_ps3(&swizzled, width, height, pixel_size), linear, "{}x{} {} byte pixels", width, height, pixel_size);
+        }
+    }
+
+    #[test]
+    fn non_power_of_two_surfaces_are_not_swizzled()
+    {
+        assert!(!can_swizzle(6, 4));
+        let data: Vec<u8> = (0..24).collect();
+        assert_eq!(deswizzle_ps3(&data, 6, 4, 1), data);
+        assert_eq!(swizzle_ps3(&data, 6, 4, 1), data);
+    }
+}
@@ -0,0 +1,152 @@
+//! Test fixtures shared by the unit tests and the integration tests, which include this file with #[path].
+//! Items are only used through vtfx_reader paths so the file compiles in both.
+#![allow(dead_code)]
+
+use vtfx_reader::image_format::{GetNumMipMapLevels, ImageFormat};
+use vtfx_reader::vtfx::{Vector, RESOURCE_ENTRY_SIZE, RESOURCE_START, VTFXHEADER, VTF_PS3_MAJOR_VERSION, VTF_PS3_MINOR_VERSION, VTF_X360_MAJOR_VERSION, VTF_X360_MINOR_VERSION};
+use vtfx_reader::vtfx_writer::PcTexture;
+
+///Data of a fixture resource
+pub enum FixtureData
+{
+    ///Stored in resData itself, e.g. a CRC or an offset that does not match the file
+    Value(u32),
+    ///Stored after the resource table, resData is its offset
+    Data(Vec<u8>),
+    ///Stored after the resource table with its u32 length first, resData is the offset of the length
+    Chunk(Vec<u8>),
+}
+
+///Fields of a console vtfx file, see build for the layout
+pub struct VtfxFixture
+{
+    pub major_version: i32,
+    pub flags: u32,
+    pub width: u16,
+    pub height: u16,
+    pub depth: u16,
+    pub frames: u16,
+    pub reflectivity: [f32; 3],
+    pub bump_scale: f32,
+    ///Not an ImageFormat so unknown formats can be written
+    pub image_format: i32,
+    pub low_res_image_sample: [u8; 4],
+    pub resources: Vec<([u8; 4], FixtureData)>,
+}
+
+impl Default for VtfxFixture
+{
+    fn default() -> Self
+    {
+        VtfxFixture {
+            major_version: VTF_X360_MAJOR_VERSION,
+            flags: 0,
+            width: 4,
+            height: 4,
+            depth: 1,
+            frames: 1,
+            reflectivity: [0.0; 3],
+            bump_scale: 1.0,
+            image_format: ImageFormat::IMAGE_FORMAT_DXT1 as i32,
+            low_res_image_sample: [0; 4],
+            resources: Vec::new(),
+        }
+    }
+}
+
+impl VtfxFixture
+{
+    ///Big endian header and resource table, followed by the resource data in resource order
+    pub fn build(&self) -> Vec<u8>
+    {
+        let header_size = RESOURCE_START + self.resources.len() * RESOURCE_ENTRY_SIZE;
+        let minor_version = match self.major_version
+        {
+            VTF_PS3_MAJOR_VERSION => VTF_PS3_MINOR_VERSION,
+            _ => VTF_X360_MINOR_VERSION,
+        };
+
+        let mut buffer = b"VTFX".to_vec();
+        buffer.extend_from_slice(&self.major_version.to_be_bytes());
+        buffer.extend_from_slice(&minor_version.to_be_bytes());
+        buffer.extend_from_slice(&(header_size as i32).to_be_bytes());
+        buffer.extend_from_slice(&self.flags.to_be_bytes());
+        for value in [self.width, self.height, self.depth, self.frames]
+        {
+            buffer.extend_from_slice(&value.to_be_bytes());
+        }
+        //Preload size, mip skip count, resource count
+        buffer.extend_from_slice(&[0, 0, 0, self.resources.len() as u8]);
+        for value in self.reflectivity.iter().chain([self.bump_scale].iter())
+        {
+            buffer.extend_from_slice(&value.to_be_bytes());
+        }
+        buffer.extend_from_slice(&self.image_format.to_be_bytes());
+        buffer.extend_from_slice(&self.low_res_image_sample);
+        //Compressed size
+        buffer.extend_from_slice(&0u32.to_be_bytes());
+        assert_eq!(buffer.len(), RESOURCE_START);
+
+        let mut data = Vec::new();
+        for (type_bytes, resource) in &self.resources
+        {
+            let res_data = match resource
+            {
+                FixtureData::Value(value) => *value,
+                FixtureData::Data(bytes) =>
+                {
+                    let offset = header_size + data.len();
+                    data.extend_from_slice(bytes);
+                    offset as u32
+                }
+                FixtureData::Chunk(bytes) =>
+                {
+                    let offset = header_size + data.len();
+                    data.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+                    data.extend_from_slice(bytes);
+                    offset as u32
+                }
+            };
+            buffer.extend_from_slice(type_bytes);
+            buffer.extend_from_slice(&res_data.to_be_bytes());
+        }
+        buffer.extend_from_slice(&data);
+        buffer
+    }
+}
+
+///Header as if parsed from a file without mips, the fields not given are left at their default
+pub fn header(major: i32, width: u16, height: u16, image_format: ImageFormat) -> VTFXHEADER
+{
+    VTFXHEADER { version: [major, 8], width, height, image_format, ..Default::default() }
+}
+
+///header with a full mip chain
+pub fn mipped_header(major: i32, width: u16, height: u16, image_format: ImageFormat) -> VTFXHEADER
+{
+    let mut vtfx = header(major, width, height, image_format);
+    vtfx.mip_count = GetNumMipMapLevels(width as i32, height as i32, 1);
+    vtfx
+}
+
+///Texture with every byte of its image data different (within 251 bytes), so any misplaced data is caught
+pub fn numbered_texture(width: u16, height: u16, image_format: ImageFormat, mip_count: usize, face_count: usize) -> PcTexture
+{
+    let mut texture = PcTexture {
+        flags: 0,
+        width,
+        height,
+        depth: 1,
+        frames: 1,
+        reflectivity: Vector { x: 0.0, y: 0.0, z: 0.0 },
+        bump_scale: 1.0,
+        image_format,
+        mip_count,
+        face_count,
+        image_data: Vec::new(),
+        low_res_data: None,
+        resources: Vec::new(),
+    };
+    texture.image_data = (0..texture.get_image_size().unwrap()).map(|byte| (byte % 251) as u8).collect();
+    texture
+}
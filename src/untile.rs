@@ -0,0 +1,238 @@
+//Xbox 360 tiled texture layout, same as XGAddress2DTiledOffset from the xbox sdk
+//https://github.com/xenia-project/xenia/blob/master/src/xenia/gpu/texture_util.cc
+
+///Width and height (in blocks) of a single tile, tiled surfaces are padded to whole tiles
+const TILE_SIZE: usize = 32;
+///Mips with a width or height (in pixels) of this size or less are packed together into a single surface (the mip tail)
+const PACKED_MIP_SIZE_LOG2: u32 = 4;
+///Size of the largest block (in bytes) the gpu has a format for (128 bit)
+const MAX_BLOCK_SIZE: usize = 16;
+///Tiled surfaces are aligned to 4KB, tiles of blocks smaller than 4 bytes extend past their own 32x32 blocks
+const SURFACE_ALIGNMENT: usize = 4096;
+
+///If blocks of this size (in bytes) can be tiled. The 360 gpu only has 8, 16, 32, 64 and 128 bit formats, 24 bit pixels are never tiled
+pub fn can_tile(block_size: usize) -> bool
+{
+    block_size.is_power_of_two() && block_size <= MAX_BLOCK_SIZE
+}
+
+///Get offset (in blocks) of block x, y in a tiled surface that is width blocks wide. block_size must be tileable (see [`can_tile`])
+pub fn get_tiled_offset(x: usize, y: usize, width: usize, block_size: usize) -> usize
+{
+    debug_assert!(can_tile(block_size), "{} byte blocks cannot be tiled", block_size);
+    let aligned_width = width.next_multiple_of(TILE_SIZE);
+    let log_bpp = block_size.trailing_zeros() as usize;
+
+    let macro_offset = ((x >> 5) + (y >> 5) * (aligned_width >> 5)) << (log_bpp + 7);
+    let micro_offset = ((x & 7) + ((y & 0xE) << 2)) << log_bpp;
+    let offset = macro_offset + ((micro_offset & !0xF) << 1) + (micro_offset & 0xF) + ((y & 1) << 4);
+
+    (((offset & !0x1FF) << 3) + ((y & 16) << 7) + ((offset & 0x1C0) << 2) + (((((y & 8) >> 2) + (x >> 3)) & 3) << 6) + (offset & 0x3F)) >> log_bpp
+}
+
+///Get size (in bytes) of a tiled surface of width x height blocks, which is padded to whole 32x32 block tiles and aligned to 4KB
+pub fn get_tiled_size(width: usize, height: usize, block_size: usize) -> usize
+{
+    (width.next_multiple_of(TILE_SIZE) * height.next_multiple_of(TILE_SIZE) * block_size).next_multiple_of(SURFACE_ALIGNMENT)
+}
+
+///Get the first mip level that is packed into the mip tail, None if every mip has its own surface.
+///Packed mips (including the first) are positioned within the surface of this level (see [`get_packed_mip_offset`]).
+///Width and height are of mip 0 in pixels. Mip 0 is never packed.
+pub fn get_mip_tail_start(width: usize, height: usize, mip_count: usize) -> Option<usize>
+{
+    let tail_start = (get_packed_mip_base(width, height) as usize).max(1);
+    (tail_start < mip_count).then_some(tail_start)
+}
+
+///Get position (in blocks) of a packed mip level in the surface of the mip tail, same as GetPackedMipOffset from xenia.
+///Width and height are of mip 0 in pixels, block_dimension is the width and height of a block in pixels (4 for dxt, otherwise 1).
+pub fn get_packed_mip_offset(width: usize, height: usize, level: usize, block_dimension: usize) -> (usize, usize)
+{
+    let log_width = width.next_power_of_two().trailing_zeros();
+    let log_height = height.next_power_of_two().trailing_zeros();
+    let packed_mip = (level as u32).saturating_sub(get_packed_mip_base(width, height));
+
+    //The 3 largest packed mips are laid out along the shorter side, smaller ones along the longer side
+    let (x, y) = match (packed_mip < 3, log_width > log_height)
+    {
+        (true, true) => (0, 16 >> packed_mip),
+        (true, false) => (16 >> packed_mip, 0),
+        (false, true) => ((1 << (log_width - get_packed_mip_base(width, height))) >> (packed_mip - 2), 0),
+        (false, false) => (0, (1 << (log_height - get_packed_mip_base(width, height))) >> (packed_mip - 2)),
+    };
+
+    (x / block_dimension, y / block_dimension)
+}
+
+///Mip level that the mip tail is positioned from, the first level with a dimension of 16 pixels or less
+fn get_packed_mip_base(width: usize, height: usize) -> u32
+{
+    let log_size = width.next_power_of_two().trailing_zeros().min(height.next_power_of_two().trailing_zeros());
+    log_size.saturating_sub(PACKED_MIP_SIZE_LOG2)
+}
+
+///Reorder tiled xbox 360 surface data into linear order.
+///Width and height are in blocks (pixels for uncompressed formats, 4x4 pixel blocks for dxt) and block_size is the size of a block in bytes.
+///Blocks that are outside of the data are left as 0.
+pub fn untile_x360(data: &[u8], width: usize, height: usize, block_size: usize) -> Vec<u8>
+{
+    untile_x360_region(data, width, 0, 0, width, height, block_size)
+}
+
+///Reorder a region of width x height blocks at x, y (in blocks) of a tiled surface that is surface_width blocks wide into linear order.
+///Used for mips packed in a mip tail. Blocks that are outside of the data are left as 0.
+pub fn untile_x360_region(data: &[u8], surface_width: usize, x: usize, y: usize, width: usize, height: usize, block_size: usize) -> Vec<u8>
+{
+    let mut linear = vec![0u8; width * height * block_size];
+    for block_y in 0..height
+    {
+        for block_x in 0..width
+        {
+            let from = get_tiled_offset(x + block_x, y + block_y, surface_width, block_size) * block_size;
+            let to = (block_x + block_y * width) * block_size;
+            if let Some(block) = data.get(from..from + block_size)
+            {
+                linear[to..to + block_size].copy_from_slice(block);
+            }
+        }
+    }
+
+    linear
+}
+
+///Reorder linear data into a tiled xbox 360 surface, padded to whole tiles (the inverse of [`untile_x360`])
+pub fn tile_x360(linear: &[u8], width: usize, height: usize, block_size: usize) -> Vec<u8>
+{
+    let mut surface = vec![0u8; get_tiled_size(width, height, block_size)];
+    tile_x360_region(linear, &mut surface, width, 0, 0, width, height, block_size);
+    surface
+}
+
+///Write linear data of width x height blocks into a region at x, y (in blocks) of a tiled surface that is surface_width blocks wide
+#[allow(clippy::too_many_arguments)]
+pub fn tile_x360_region(linear: &[u8], surface: &mut [u8], surface_width: usize, x: usize, y: usize, width: usize, height: usize, block_size: usize)
+{
+    for block_y in 0..height
+    {
+        for block_x in 0..width
+        {
+            let to = get_tiled_offset(x + block_x, y + block_y, surface_width, block_size) * block_size;
+            let from = (block_x + block_y * width) * block_size;
+            if let (Some(block), Some(tiled)) = (linear.get(from..from + block_size), surface.get_mut(to..to + block_size))
+            {
+                tiled.copy_from_slice(block);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn tiled_offsets_match_known_layout()
+    {
+        //Offsets (in blocks) of 32 bit pixels in a 32x32 tile, from XGAddress2DTiledOffset
+        let offsets: Vec<usize> = (0..10).map(|x| get_tiled_offset(x, 0, 32, 4)).collect();
+        assert_eq!(offsets, [0, 1, 2, 3, 8, 9, 10, 11, 16, 17]);
+        let offsets: Vec<usize> = (0..4).map(|y| get_tiled_offset(0, y, 32, 4)).collect();
+        assert_eq!(offsets, [0, 4, 64, 68]);
+        assert_eq!(get_tiled_offset(31, 31, 32, 4), 991);
+        assert_eq!(get_tiled_offset(0, 32, 32, 4), 1024);
+        assert_eq!(get_tiled_offset(32, 0, 64, 4), 1024);
+    }
+
+    #[test]
+    fn tiled_offsets_depend_on_block_size()
+    {
+        //dxt1 (8 byte) and dxt5 (16 byte) blocks have a different micro tile layout to 32 bit pixels
+        let dxt1: Vec<usize> = [(1, 0), (0, 1), (8, 0), (0, 2)].iter().map(|&(x, y)| get_tiled_offset(x, y, 32, 8)).collect();
+        assert_eq!(dxt1, [1, 2, 8, 64]);
+        let dxt5: Vec<usize> = [(1, 0), (0, 1), (8, 0), (0, 2)].iter().map(|&(x, y)| get_tiled_offset(x, y, 32, 16)).collect();
+        assert_eq!(dxt5, [2, 1, 4, 64]);
+        let a8: Vec<usize> = [(1, 0), (0, 1), (8, 0), (0, 2)].iter().map(|&(x, y)| get_tiled_offset(x, y, 32, 1)).collect();
+        assert_eq!(a8, [1, 16, 64, 8]);
+    }
+
+    #[test]
+    fn untile_reorders_known_pair()
+    {
+        //4x4 32 bit pixels in a tiled 32x32 surface, each pixel is its linear index
+        let mut tiled = vec![0u8; get_tiled_size(4, 4, 4)];
+        assert_eq!(tiled.len(), 4096);
+        for (index, offset) in [0, 1, 2, 3, 4, 5, 6, 7, 64, 65, 66, 67, 68, 69, 70, 71].iter().enumerate()
+        {
+            tiled[offset * 4] = index as u8;
+        }
+        let linear = untile_x360(&tiled, 4, 4, 4);
+        let order: Vec<u8> = linear.chunks_exact(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(order, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]);
+
+        //Pixels 4 to 7 of row 0 are after the first 4 pixels of row 1
+        let mut tiled = vec![0u8; get_tiled_size(16, 1, 4)];
+        tiled[8 * 4] = 0xAB;
+        assert_eq!(untile_x360(&tiled, 16, 1, 4)[4 * 4], 0xAB);
+    }
+
+    #[test]
+    fn tile_and_untile_round_trip()
+    {
+        for (width, height, block_size) in [(64, 48, 4), (5, 3, 8), (40, 70, 1), (33, 2, 16), (1, 1, 2), (32, 32, 1)]
+        {
+            let linear: Vec<u8> = (0..width * height * block_size).map(|i| (i * 7 % 251) as u8).collect();
+            let tiled = tile_x360(&linear, width, height, block_size);
+            assert_eq!(tiled.len() % SURFACE_ALIGNMENT, 0);
+            assert_eq!(untile_x360(&tiled, width, height, block_size), linear, "{}x{} {} byte blocks", width, height, block_size);
+        }
+    }
+
+    #[test]
+    fn only_power_of_two_blocks_are_tileable()
+    {
+        assert!([1, 2, 4, 8, 16].into_iter().all(can_tile));
+        assert!(![3, 6, 12, 32].into_iter().any(can_tile));
+    }
+
+    #[test]
+    fn mip_tail_starts_at_16_pixels()
+    {
+        //256x256: mip 4 is 16x16
+        assert_eq!(get_mip_tail_start(256, 256, 9), Some(4));
+        //256x64: mip 2 is 64x16
+        assert_eq!(get_mip_tail_start(256, 64, 9), Some(2));
+        //Mip 0 is never packed
+        assert_eq!(get_mip_tail_start(16, 16, 5), Some(1));
+        //No mip is small enough
+        assert_eq!(get_mip_tail_start(1024, 1024, 1), None);
+        assert_eq!(get_mip_tail_start(1024, 1024, 6), None);
+    }
+
+    #[test]
+    fn packed_mips_do_not_overlap()
+    {
+        //256x256 dxt: 16x16 at 4 blocks across, then 8x8 and 4x4, 2x2 and 1x1 go down
+        let offsets: Vec<(usize, usize)> = (4..9).map(|level| get_packed_mip_offset(256, 256, level, 4)).collect();
+        assert_eq!(offsets, [(4, 0), (2, 0), (1, 0), (0, 2), (0, 1)]);
+
+        //Uncompressed wide texture, mips 2 to 4 go down, smaller ones across
+        let offsets: Vec<(usize, usize)> = (2..9).map(|level| get_packed_mip_offset(256, 64, level, 1)).collect();
+        assert_eq!(offsets, [(0, 16), (0, 8), (0, 4), (32, 0), (16, 0), (8, 0), (4, 0)]);
+
+        let mut used = vec![false; 64 * 32];
+        for (level, (x, y)) in (2..9).zip(offsets)
+        {
+            let (width, height) = ((256 >> level) as usize, ((64 >> level) as usize).max(1));
+            for pixel_y in y..y + height
+            {
+                for pixel_x in x..x + width
+                {
+                    assert!(!used[pixel_x + pixel_y * 64], "mip {} overlaps at {}, {}", level, pixel_x, pixel_y);
+                    used[pixel_x + pixel_y * 64] = true;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use image::{DynamicImage, GenericImage, GenericImageView};
+
+use crate::error::VtfxError;
+
+///Arrange volume slices left to right into a single image (e.g. a 32x32x32 colour correction lut becomes 1024x32)
+pub fn slices_to_strip(slices: &[DynamicImage]) -> Result<DynamicImage, VtfxError>
+{
+    let first = slices.first().ok_or(VtfxError::SliceOutOfRange { slice: 0, slice_count: 0 })?;
+    let (slice_width, slice_height) = first.dimensions();
+    let mut strip = DynamicImage::new_rgba8(slice_width * slices.len() as u32, slice_height);
+    for (slice_num, slice) in slices.iter().enumerate()
+    {
+        strip.copy_from(&slice.to_rgba8(), slice_num as u32 * slice_width, 0)?;
+    }
+
+    Ok(strip)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn slices_are_placed_left_to_right()
+    {
+        //2x3 slices where red is the slice number
+        let slices: Vec<DynamicImage> = (0..4u8).map(|slice| DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 3, Rgba([slice, 0, 0, 255])))).collect();
+        let strip = slices_to_strip(&slices).unwrap().to_rgba8();
+        assert_eq!(strip.dimensions(), (8, 3));
+        for (x, y, pixel) in strip.enumerate_pixels()
+        {
+            assert_eq!(pixel.0, [(x / 2) as u8, 0, 0, 255], "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn empty_volume_is_rejected()
+    {
+        assert!(matches!(slices_to_strip(&[]), Err(VtfxError::SliceOutOfRange { slice: 0, slice_count: 0 })));
+    }
+}
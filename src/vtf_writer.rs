@@ -0,0 +1,360 @@
+//Writes PC (little endian) vtf files from vtfx files without decoding, so conversion is lossless
+//https://developer.valvesoftware.com/wiki/VTF_(Valve_Texture_Format)#VTF_layout
+
+use std::io::Write;
+
+use crate::decode::{low_res_resource_to_data, resource_to_mips, DecodeOptions};
+use crate::error::VtfxError;
+use crate::image_format::{correct_endianness, ImageFormat};
+use crate::resource::{Resource, Sheet};
+use crate::resource_entry_info::ResourceType;
+use crate::vtfx::{VTFXHEADER, CUBEMAP_FACE_COUNT, LOW_RES_IMAGE_FORMAT, VTF_LEGACY_RSRC_IMAGE, VTF_LEGACY_RSRC_LOW_RES_IMAGE, VTF_RSRC_KEY_VALUE_DATA, VTF_RSRC_SHEET};
+
+pub(crate) const VTF_MAJOR_VERSION: u32 = 7;
+pub const VTF_MIN_MINOR_VERSION: u32 = 2;
+pub const VTF_MAX_MINOR_VERSION: u32 = 5;
+///Versions from 7.3 have a resource table
+pub(crate) const VTF_MINOR_VERSION_MIN_RESOURCE: u32 = 3;
+///Versions from 7.5 never have a spheremap face
+pub(crate) const VTF_MINOR_VERSION_MIN_NO_SPHERE_MAP: u32 = 5;
+///Size of the header (all versions are padded to 80 bytes), the resource table follows it
+pub(crate) const VTF_HEADER_SIZE: usize = 80;
+const RESOURCE_ENTRY_SIZE: usize = 8;
+///Start frame value that marks a cubemap as having no spheremap face (versions before 7.5)
+pub(crate) const NO_SPHERE_MAP_START_FRAME: u16 = 0xFFFF;
+
+///A resource to write, either a value stored in the resource table or a data chunk
+#[derive(Debug, Clone)]
+pub enum VtfResource
+{
+    Value { type_bytes: [u8; 4], value: u32 },
+    Data { type_bytes: [u8; 4], data: Vec<u8> },
+}
+
+///Convert a vtfx to a PC vtf of version 7.minor_version (2 to 5) and write it.
+///Image data is copied without decoding (dxt stays compressed), only its byte order is converted.
+///Resources of unknown type are not written as their byte order is unknown.
+pub fn write_vtf<W: Write>(buffer: &[u8], vtfx: &VTFXHEADER, options: &DecodeOptions, minor_version: u32, mut writer: W) -> Result<(), VtfxError>
+{
+    if !(VTF_MIN_MINOR_VERSION..=VTF_MAX_MINOR_VERSION).contains(&minor_version)
+    {
+        return Err(VtfxError::UnsupportedVtfVersion { major: VTF_MAJOR_VERSION, minor: minor_version });
+    }
+
+    let pc_format = vtfx.image_format.get_pc_format().ok_or(VtfxError::NoPcFormat(vtfx.image_format))?;
+    let resource_entry_infos = vtfx.get_resource_entry_infos(buffer)?;
+    let image_entry_info = resource_entry_infos.iter()
+        .find(|info| info.get_type() == ResourceType::VTF_LEGACY_RSRC_IMAGE)
+        .ok_or(VtfxError::NoImageResource)?;
+    let mips = resource_to_mips(buffer, image_entry_info, vtfx, options)?;
+
+    //Vtfx cubemaps never have a spheremap face, versions before 7.5 must mark that with the start frame
+    let face_count = mips.face_count();
+    let start_frame = match vtfx.is_envmap() && face_count == CUBEMAP_FACE_COUNT && minor_version < VTF_MINOR_VERSION_MIN_NO_SPHERE_MAP
+    {
+        true => NO_SPHERE_MAP_START_FRAME,
+        false => 0
+    };
+
+    //Mips are stored smallest to largest, each mip has all frames, each frame all faces and each face all slices
+    let fix_dxt = vtfx.is_xbox() || options.force_dxt_endian_fix;
+    let mut image_data = Vec::new();
+    for level in (0..mips.mip_count()).rev()
+    {
+        for frame in 0..mips.frame_count()
+        {
+            for face in 0..face_count
+            {
+                for slice in 0..mips.slice_count(level)
+                {
+                    let mut slice_data = mips.get_mip_slice_data(level, frame, face, slice)?;
+                    correct_endianness(vtfx.image_format, &mut slice_data, fix_dxt)?;
+                    image_data.extend_from_slice(&slice_data);
+                }
+            }
+        }
+    }
+
+    let low_res_data = match resource_entry_infos.iter().find(|info| info.get_type() == ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE)
+    {
+        Some(low_res_entry_info) => Some(low_res_resource_to_data(buffer, low_res_entry_info, vtfx, options)?),
+        None => None
+    };
+
+    let mut resources = Vec::new();
+    if minor_version >= VTF_MINOR_VERSION_MIN_RESOURCE
+    {
+        for (res_num, resource_entry_info) in resource_entry_infos.iter().enumerate()
+        {
+            let type_bytes = resource_entry_info.chTypeBytes;
+            match Resource::parse(buffer, resource_entry_info, res_num)?
+            {
+                //Values were byte swapped as a whole u32, so are written back as a little endian u32
+                Resource::Crc(_) | Resource::LodSettings(_) | Resource::SettingsEx(_) => resources.push(VtfResource::Value { type_bytes, value: resource_entry_info.resData }),
+                Resource::Sheet(sheet) => resources.push(VtfResource::Data { type_bytes: VTF_RSRC_SHEET, data: sheet_to_le_bytes(&sheet) }),
+                Resource::KeyValueData(text) => resources.push(VtfResource::Data { type_bytes: VTF_RSRC_KEY_VALUE_DATA, data: text.into_bytes() }),
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(low_res_data) = &low_res_data
+    {
+        resources.push(VtfResource::Data { type_bytes: VTF_LEGACY_RSRC_LOW_RES_IMAGE, data: low_res_data.clone() });
+    }
+    resources.push(VtfResource::Data { type_bytes: VTF_LEGACY_RSRC_IMAGE, data: image_data });
+    resources.sort_by_key(|resource| match resource
+    {
+        VtfResource::Value { type_bytes, .. } | VtfResource::Data { type_bytes, .. } => *type_bytes
+    });
+
+    let header_size = match minor_version >= VTF_MINOR_VERSION_MIN_RESOURCE
+    {
+        true => VTF_HEADER_SIZE + resources.len() * RESOURCE_ENTRY_SIZE,
+        false => VTF_HEADER_SIZE
+    };
+
+    let (low_res_format, (low_res_width, low_res_height)) = match low_res_data
+    {
+        Some(_) => (LOW_RES_IMAGE_FORMAT, vtfx.get_low_res_size()),
+        None => (ImageFormat::IMAGE_FORMAT_UNKNOWN, (0, 0))
+    };
+
+    let mut header = Vec::with_capacity(header_size);
+    header.extend_from_slice(b"VTF\0");
+    header.extend_from_slice(&VTF_MAJOR_VERSION.to_le_bytes());
+    header.extend_from_slice(&minor_version.to_le_bytes());
+    header.extend_from_slice(&(header_size as u32).to_le_bytes());
+    header.extend_from_slice(&vtfx.width.to_le_bytes());
+    header.extend_from_slice(&vtfx.height.to_le_bytes());
+    header.extend_from_slice(&vtfx.flags.to_le_bytes());
+    header.extend_from_slice(&(mips.frame_count() as u16).to_le_bytes());
+    header.extend_from_slice(&start_frame.to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&vtfx.reflectivity.x.to_le_bytes());
+    header.extend_from_slice(&vtfx.reflectivity.y.to_le_bytes());
+    header.extend_from_slice(&vtfx.reflectivity.z.to_le_bytes());
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&vtfx.bump_scale.to_le_bytes());
+    header.extend_from_slice(&(pc_format as i32).to_le_bytes());
+    header.push(mips.mip_count() as u8);
+    header.extend_from_slice(&(low_res_format as i32).to_le_bytes());
+    header.push(low_res_width as u8);
+    header.push(low_res_height as u8);
+    header.extend_from_slice(&(vtfx.get_depth() as u16).to_le_bytes());
+    header.extend_from_slice(&[0; 3]);
+    let num_resources = match minor_version >= VTF_MINOR_VERSION_MIN_RESOURCE
+    {
+        true => resources.len() as u32,
+        false => 0
+    };
+    header.extend_from_slice(&num_resources.to_le_bytes());
+    header.resize(VTF_HEADER_SIZE, 0);
+
+    //Resource table, data chunks follow in the same order. Images are not prefixed by their size.
+    //Versions before 7.3 have no table, the low res image is followed by the image.
+    let mut offset = header_size;
+    for resource in resources.iter().filter(|_| minor_version >= VTF_MINOR_VERSION_MIN_RESOURCE)
+    {
+        match resource
+        {
+            VtfResource::Value { type_bytes, value } => {
+                header.extend_from_slice(type_bytes);
+                header.extend_from_slice(&value.to_le_bytes());
+            },
+            VtfResource::Data { type_bytes, data } => {
+                header.extend_from_slice(type_bytes);
+                header.extend_from_slice(&(offset as u32).to_le_bytes());
+                offset += data.len() + get_chunk_length_size(type_bytes);
+            }
+        }
+    }
+
+    let mut output = header;
+    for resource in &resources
+    {
+        if let VtfResource::Data { type_bytes, data } = resource
+        {
+            if get_chunk_length_size(type_bytes) > 0
+            {
+                output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            }
+            output.extend_from_slice(data);
+        }
+    }
+
+    writer.write_all(&output)?;
+
+    Ok(())
+}
+
+///Size of the length that prefixes a data chunk, images have no length
+pub(crate) fn get_chunk_length_size(type_bytes: &[u8; 4]) -> usize
+{
+    match *type_bytes == VTF_LEGACY_RSRC_IMAGE || *type_bytes == VTF_LEGACY_RSRC_LOW_RES_IMAGE
+    {
+        true => 0,
+        false => 4
+    }
+}
+
+///Write sheet in the same layout as it is read, as little endian
+fn sheet_to_le_bytes(sheet: &Sheet) -> Vec<u8>
+{
+    let mut data = Vec::new();
+    data.extend_from_slice(&sheet.version.to_le_bytes());
+    data.extend_from_slice(&(sheet.sequences.len() as i32).to_le_bytes());
+    for sequence in &sheet.sequences
+    {
+        data.extend_from_slice(&sequence.sequence_number.to_le_bytes());
+        data.extend_from_slice(&(sequence.clamp as i32).to_le_bytes());
+        data.extend_from_slice(&(sequence.frames.len() as i32).to_le_bytes());
+        data.extend_from_slice(&sequence.total_time.to_le_bytes());
+        for frame in &sequence.frames
+        {
+            data.extend_from_slice(&frame.duration.to_le_bytes());
+            for coord in frame.coords.iter().flatten()
+            {
+                data.extend_from_slice(&coord.to_le_bytes());
+            }
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_util::{FixtureData, VtfxFixture};
+    use crate::vtfx::{TEXTUREFLAGS_ENVMAP, TEXTUREFLAGS_NOMIP, VTF_PS3_MAJOR_VERSION, VTF_RSRC_TEXTURE_CRC};
+    use crate::vtfx_writer::PcTexture;
+
+    const CRC: u32 = 0x12345678;
+    ///Each face of the 4x4 DXT1 cubemap is a single block, PS3 dxt data is the same as PC
+    const FACE_SIZE: usize = 8;
+    const LOW_RES_DATA: [u8; 8] = [0xAA; 8];
+
+    fn face_data() -> Vec<u8>
+    {
+        (0..CUBEMAP_FACE_COUNT * FACE_SIZE).map(|byte| byte as u8).collect()
+    }
+
+    ///PS3 4x4 DXT1 cubemap with no mips, a low res image and a CRC
+    fn build_cubemap() -> Vec<u8>
+    {
+        VtfxFixture {
+            major_version: VTF_PS3_MAJOR_VERSION,
+            flags: TEXTUREFLAGS_NOMIP | TEXTUREFLAGS_ENVMAP,
+            reflectivity: [0.25, 0.5, 0.75],
+            bump_scale: 2.0,
+            resources: vec![
+                (VTF_LEGACY_RSRC_LOW_RES_IMAGE, FixtureData::Data(LOW_RES_DATA.to_vec())),
+                (VTF_LEGACY_RSRC_IMAGE, FixtureData::Data(face_data())),
+                (VTF_RSRC_TEXTURE_CRC, FixtureData::Value(CRC)),
+            ],
+            ..Default::default()
+        }.build()
+    }
+
+    fn write(minor_version: u32) -> Vec<u8>
+    {
+        let buffer = build_cubemap();
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        let mut output = Vec::new();
+        write_vtf(&buffer, &vtfx, &DecodeOptions::default(), minor_version, &mut output).unwrap();
+        output
+    }
+
+    fn read_u16(buffer: &[u8], offset: usize) -> u16
+    {
+        u16::from_le_bytes([buffer[offset], buffer[offset + 1]])
+    }
+
+    fn read_u32(buffer: &[u8], offset: usize) -> u32
+    {
+        u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]])
+    }
+
+    ///Check the 80 byte header that is the same for every version, except for the header size, start frame and resource count
+    fn assert_header(output: &[u8], minor_version: u32, header_size: u32, start_frame: u16, num_resources: u32)
+    {
+        assert_eq!(&output[0..4], b"VTF\0");
+        assert_eq!((read_u32(output, 4), read_u32(output, 8), read_u32(output, 12)), (VTF_MAJOR_VERSION, minor_version, header_size));
+        assert_eq!((read_u16(output, 16), read_u16(output, 18)), (4, 4));
+        assert_eq!(read_u32(output, 20), TEXTUREFLAGS_NOMIP | TEXTUREFLAGS_ENVMAP);
+        assert_eq!((read_u16(output, 24), read_u16(output, 26)), (1, start_frame));
+        let reflectivity: Vec<f32> = [32, 36, 40, 48].iter().map(|offset| f32::from_bits(read_u32(output, *offset))).collect();
+        assert_eq!(reflectivity, [0.25, 0.5, 0.75, 2.0]);
+        assert_eq!(read_u32(output, 52), ImageFormat::IMAGE_FORMAT_DXT1 as u32);
+        //Mip count, low res format and size
+        assert_eq!(output[56], 1);
+        assert_eq!(read_u32(output, 57), LOW_RES_IMAGE_FORMAT as u32);
+        assert_eq!((output[61], output[62]), (4, 4));
+        assert_eq!(read_u16(output, 63), 1);
+        assert_eq!(read_u32(output, 68), num_resources);
+    }
+
+    ///Read the vtf back and check it has the same texture
+    fn assert_round_trip(output: &[u8], has_crc: bool)
+    {
+        let texture = PcTexture::from_vtf(output).unwrap();
+        assert_eq!((texture.width, texture.height, texture.depth, texture.frames), (4, 4, 1, 1));
+        assert_eq!((texture.image_format, texture.mip_count, texture.face_count), (ImageFormat::IMAGE_FORMAT_DXT1, 1, CUBEMAP_FACE_COUNT));
+        assert_eq!(texture.bump_scale, 2.0);
+        assert_eq!(texture.image_data, face_data());
+        assert_eq!(texture.low_res_data, Some(LOW_RES_DATA.to_vec()));
+        assert_eq!(has_crc, texture.resources.iter().any(|resource| matches!(resource, VtfResource::Value { type_bytes: VTF_RSRC_TEXTURE_CRC, value: CRC })));
+    }
+
+    #[test]
+    fn version_7_2_has_no_resource_table()
+    {
+        let output = write(2);
+        assert_header(&output, 2, VTF_HEADER_SIZE as u32, NO_SPHERE_MAP_START_FRAME, 0);
+        //Low res image then the image straight after the header
+        assert_eq!(output.len(), VTF_HEADER_SIZE + LOW_RES_DATA.len() + CUBEMAP_FACE_COUNT * FACE_SIZE);
+        assert_eq!(&output[VTF_HEADER_SIZE..VTF_HEADER_SIZE + LOW_RES_DATA.len()], LOW_RES_DATA);
+        assert_round_trip(&output, false);
+    }
+
+    #[test]
+    fn version_7_4_has_resource_table_and_no_sphere_map_start_frame()
+    {
+        let output = write(4);
+        let header_size = VTF_HEADER_SIZE + 3 * RESOURCE_ENTRY_SIZE;
+        assert_header(&output, 4, header_size as u32, NO_SPHERE_MAP_START_FRAME, 3);
+
+        //Sorted by type, images have no length before their data
+        let table: Vec<([u8; 4], u32)> = output[VTF_HEADER_SIZE..header_size].chunks_exact(RESOURCE_ENTRY_SIZE)
+            .map(|entry| ([entry[0], entry[1], entry[2], entry[3]], read_u32(entry, 4)))
+            .collect();
+        assert_eq!(table, [
+            (VTF_LEGACY_RSRC_LOW_RES_IMAGE, header_size as u32),
+            (VTF_LEGACY_RSRC_IMAGE, (header_size + LOW_RES_DATA.len()) as u32),
+            (VTF_RSRC_TEXTURE_CRC, CRC),
+        ]);
+        assert_eq!(&output[header_size + LOW_RES_DATA.len()..], face_data());
+        assert_round_trip(&output, true);
+    }
+
+    #[test]
+    fn version_7_5_never_has_a_sphere_map()
+    {
+        let output = write(5);
+        let header_size = VTF_HEADER_SIZE + 3 * RESOURCE_ENTRY_SIZE;
+        assert_header(&output, 5, header_size as u32, 0, 3);
+        assert_eq!(output.len(), header_size + LOW_RES_DATA.len() + CUBEMAP_FACE_COUNT * FACE_SIZE);
+        assert_round_trip(&output, true);
+    }
+
+    #[test]
+    fn unsupported_version_is_rejected()
+    {
+        let buffer = build_cubemap();
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        let error = write_vtf(&buffer, &vtfx, &DecodeOptions::default(), 6, Vec::new()).unwrap_err();
+        assert!(matches!(error, VtfxError::UnsupportedVtfVersion { major: 7, minor: 6 }), "{:?}", error);
+    }
+}
@@ -1,19 +1,45 @@
-const VTF_X360_MAJOR_VERSION: i32 = 0x0360;
-const VTF_X360_MINOR_VERSION: i32 = 8;
+pub const VTF_X360_MAJOR_VERSION: i32 = 0x0360;
+pub const VTF_X360_MINOR_VERSION: i32 = 8;
+pub const VTF_PS3_MAJOR_VERSION: i32 = 0x0333;
+pub const VTF_PS3_MINOR_VERSION: i32 = 8;
 
 pub const VTF_LEGACY_RSRC_IMAGE: [u8;4] = [0x30, 0, 0, 0];
 pub const VTF_LEGACY_RSRC_LOW_RES_IMAGE: [u8;4] = [0x01, 0, 0, 0];
+pub const VTF_RSRC_SHEET: [u8;4] = [0x10, 0, 0, 0];
+pub const VTF_RSRC_TEXTURE_CRC: [u8;4] = [b'C', b'R', b'C', RSRCF_HAS_NO_DATA_CHUNK];
+pub const VTF_RSRC_TEXTURE_LOD_SETTINGS: [u8;4] = [b'L', b'O', b'D', RSRCF_HAS_NO_DATA_CHUNK];
+pub const VTF_RSRC_TEXTURE_SETTINGS_EX: [u8;4] = [b'T', b'S', b'0', RSRCF_HAS_NO_DATA_CHUNK];
+pub const VTF_RSRC_KEY_VALUE_DATA: [u8;4] = [b'K', b'V', b'D', 0];
 
-use std::{error::Error, mem, io, fmt};
+///Low res images are always DXT1 and fit within LOW_RES_IMAGE_SIZE
+pub const LOW_RES_IMAGE_FORMAT: ImageFormat = ImageFormat::IMAGE_FORMAT_DXT1;
+const LOW_RES_IMAGE_SIZE: usize = 16;
+
+///Number of cubemap faces. Unlike PC vtf before 7.5, vtfx never has a spheremap face (vtf_x360.cpp reads CUBEMAP_FACE_COUNT-1 faces)
+pub const CUBEMAP_FACE_COUNT: usize = 6;
+
+//https://github.com/ValveSoftware/source-sdk-2013/blob/master/sp/src/public/vtf/vtf.h
+pub const RSRCF_HAS_NO_DATA_CHUNK: u8 = 0x02;
+
+pub const TEXTUREFLAGS_HINT_DXT5: u32 = 0x0020;
+pub const TEXTUREFLAGS_NOMIP: u32 = 0x0100;
+pub const TEXTUREFLAGS_ALL_MIPS: u32 = 0x0400;
+pub const TEXTUREFLAGS_ONEBITALPHA: u32 = 0x1000;
+pub const TEXTUREFLAGS_EIGHTBITALPHA: u32 = 0x2000;
+pub const TEXTUREFLAGS_ENVMAP: u32 = 0x4000;
+
+use std::{mem, fmt};
 
 use num_enum::TryFromPrimitive;
 
-use crate::{ImageFormat, resource_entry_info::ResourceEntryInfo, image_format::GetNumMipMapLevels};
+use crate::{ImageFormat, resource_entry_info::ResourceEntryInfo, image_format::GetNumMipMapLevels, error::VtfxError, resource::Resource};
 
-const RESOURCE_START: usize = 60;
+pub const RESOURCE_START: usize = 60;
+pub const RESOURCE_ENTRY_SIZE: usize = 8;
 
 #[repr(C)]
 #[derive(Debug, Default)]
+#[allow(clippy::upper_case_acronyms)] //Keep name same as source
 //https://developer.valvesoftware.com/wiki/VTFX_file_format
 //https://github.com/ValveSoftware/source-sdk-2013/blob/master/sp/src/public/vtf/vtf.h#L551
 pub struct VTFXHEADER {
@@ -38,77 +64,78 @@ pub struct VTFXHEADER {
 
 impl VTFXHEADER
 {
-    pub fn from(buffer: &[u8]) -> Result<VTFXHEADER, Box<dyn Error>>
+    pub fn from(buffer: &[u8]) -> Result<VTFXHEADER, VtfxError>
     {
-        let type_str_range = &buffer[0..4];
-        let type_str = std::str::from_utf8(&type_str_range)?;
-        if type_str != "VTFX"
+        let magic: [u8; 4] = read_bytes(buffer, 0)?;
+        if &magic != b"VTFX"
         {
-            let err = io::Error::new(io::ErrorKind::Other, "File is not VTFX file!");
-            return Err(Box::new(err));
+            return Err(VtfxError::BadMagic { found: magic });
         }
 
         let mut vtfx: VTFXHEADER = { Default::default() };
 
         let mut i = 0;
-        vtfx.file_type_string = String::from(type_str);
+        vtfx.file_type_string = String::from("VTFX");
         i += 4;
         
         let mut version: [i32; 2] = [0; 2];
-        for j in 0..2 {
-            let start = i + (j * 4);
-            let end = start + 4;
-            let slice: &[u8] = &buffer[start..end];
-            let value: i32 = i32::from_be_bytes(slice.try_into().unwrap());
-            version[j] = value;
+        for (j, version_part) in version.iter_mut().enumerate() {
+            *version_part = i32::from_be_bytes(read_bytes(buffer, i + (j * 4))?);
         }
         i += 4 * 2;
         vtfx.version = version;
 
-        vtfx.header_size = i32::from_be_bytes(buffer[i..i+4].try_into().unwrap());
+        if version[0] != VTF_X360_MAJOR_VERSION && version[0] != VTF_PS3_MAJOR_VERSION
+        {
+            return Err(VtfxError::UnsupportedVersion { major: version[0], minor: version[1] });
+        }
+
+        vtfx.header_size = i32::from_be_bytes(read_bytes(buffer, i)?);
         i += 4;
 
-        vtfx.flags = u32::from_be_bytes(buffer[i..i+4].try_into().unwrap());
+        vtfx.flags = u32::from_be_bytes(read_bytes(buffer, i)?);
         i += 4;
 
-        vtfx.width = u16::from_be_bytes(buffer[i..i+2].try_into().unwrap());
+        vtfx.width = u16::from_be_bytes(read_bytes(buffer, i)?);
         i += 2;
 
-        vtfx.height = u16::from_be_bytes(buffer[i..i+2].try_into().unwrap());
+        vtfx.height = u16::from_be_bytes(read_bytes(buffer, i)?);
         i += 2;
 
-        vtfx.depth = u16::from_be_bytes(buffer[i..i+2].try_into().unwrap());
+        vtfx.depth = u16::from_be_bytes(read_bytes(buffer, i)?);
         i += 2;
 
-        vtfx.num_frames = u16::from_be_bytes(buffer[i..i+2].try_into().unwrap());
+        vtfx.num_frames = u16::from_be_bytes(read_bytes(buffer, i)?);
         i += 2;
 
-        vtfx.preload_data_size = u16::from_be_bytes(buffer[i..i+2].try_into().unwrap());
+        vtfx.preload_data_size = u16::from_be_bytes(read_bytes(buffer, i)?);
         i += 2;
 
-        vtfx.mip_skip_count = u8::from_be_bytes(buffer[i..i+1].try_into().unwrap());
+        vtfx.mip_skip_count = u8::from_be_bytes(read_bytes(buffer, i)?);
         i += 1;
 
-        vtfx.num_resources = u8::from_be_bytes(buffer[i..i+1].try_into().unwrap());
+        vtfx.num_resources = u8::from_be_bytes(read_bytes(buffer, i)?);
         i += 1;
 
-        //vtfx.reflectivity
+        vtfx.reflectivity = Vector {
+            x: f32::from_be_bytes(read_bytes(buffer, i)?),
+            y: f32::from_be_bytes(read_bytes(buffer, i + 4)?),
+            z: f32::from_be_bytes(read_bytes(buffer, i + 8)?),
+        };
         i += mem::size_of::<Vector>();
 
-        vtfx.bump_scale = f32::from_be_bytes(buffer[i..i+4].try_into().unwrap());
-        i += 4;
-
-        let image_format_i32 = i32::from_be_bytes(buffer[i..i+4].try_into().unwrap());
-        vtfx.image_format = ImageFormat::try_from_primitive(image_format_i32).unwrap();
+        vtfx.bump_scale = f32::from_be_bytes(read_bytes(buffer, i)?);
         i += 4;
 
-        //vtfx.low_res_image_sample
+        let image_format_i32 = i32::from_be_bytes(read_bytes(buffer, i)?);
+        vtfx.image_format = ImageFormat::try_from_primitive(image_format_i32)
+            .map_err(|_| VtfxError::UnknownImageFormat { id: image_format_i32, offset: i })?;
         i += 4;
 
-        vtfx.compressed_size = u32::from_be_bytes(buffer[i..i+4].try_into().unwrap());
+        vtfx.low_res_image_sample = read_bytes(buffer, i)?;
         i += 4;
 
-        if cfg!(debug_assertions){ println!("[Debug] VTFX READ END: Current read position: {}, Data left: {} bytes", i, buffer.len() - i); }
+        vtfx.compressed_size = u32::from_be_bytes(read_bytes(buffer, i)?);
 
         vtfx.mip_count = match vtfx.no_mips() {
             true => 1,
@@ -118,24 +145,45 @@ impl VTFXHEADER
         Ok(vtfx)
     }
 
-    pub fn get_resource_entry_infos(&self, buffer: &[u8]) -> Vec<ResourceEntryInfo>
+    ///Read the resource table. Resources that point outside of the file are rejected.
+    pub fn get_resource_entry_infos(&self, buffer: &[u8]) -> Result<Vec<ResourceEntryInfo>, VtfxError>
     {
+        let table_size = self.num_resources as usize * RESOURCE_ENTRY_SIZE;
+        if buffer.len() < RESOURCE_START + table_size
+        {
+            return Err(VtfxError::TruncatedHeader { offset: RESOURCE_START, needed: table_size, available: buffer.len() });
+        }
+
         let mut resource_entry_infos: Vec<ResourceEntryInfo> = Vec::new();
         let mut i = RESOURCE_START;
-        for _res_num in 0..self.num_resources
+        for res_num in 0..self.num_resources as usize
         {
             let mut resource_entry_info: ResourceEntryInfo = { Default::default() };
-            for x in 0..3
-            {
-                resource_entry_info.chTypeBytes[x] = buffer[i+x];
-            }
+            resource_entry_info.chTypeBytes = read_bytes(buffer, i)?;
             i += 4;
-            resource_entry_info.resData = u32::from_be_bytes(buffer[i..i+4].try_into().unwrap());
+            resource_entry_info.resData = u32::from_be_bytes(read_bytes(buffer, i)?);
             i += 4;
+
+            //Resources with no data chunk store their value in resData instead of an offset
+            if resource_entry_info.has_data_chunk() && resource_entry_info.resData as usize >= buffer.len()
+            {
+                return Err(VtfxError::ResourceOutOfBounds { resource: res_num, offset: resource_entry_info.resData as usize, file_size: buffer.len() });
+            }
+
             resource_entry_infos.push(resource_entry_info);
         }
 
-        resource_entry_infos
+        Ok(resource_entry_infos)
+    }
+
+    ///Read the resource table and parse every resource into a typed value
+    pub fn get_resources(&self, buffer: &[u8]) -> Result<Vec<Resource>, VtfxError>
+    {
+        self.get_resource_entry_infos(buffer)?
+            .iter()
+            .enumerate()
+            .map(|(res_num, resource_entry_info)| Resource::parse(buffer, resource_entry_info, res_num))
+            .collect()
     }
 
     pub fn get_channels(&self) -> u16
@@ -145,61 +193,65 @@ impl VTFXHEADER
             return 4;
         }
 
-        return 3;
+        3
     }
 
     pub fn has_alpha(&self) -> bool
     {
-        (self.flags & 0x2000) != 0
+        (self.flags & TEXTUREFLAGS_EIGHTBITALPHA) != 0
     }
 
     pub fn has_onebit_alpha(&self) -> bool
     {
-        (self.flags & 0x1000) != 0
+        (self.flags & TEXTUREFLAGS_ONEBITALPHA) != 0
     }
 
     pub fn all_mips(&self) -> bool
     {
-        (self.flags & 0x00000400) != 0
+        (self.flags & TEXTUREFLAGS_ALL_MIPS) != 0
     }
 
     pub fn no_mips(&self) -> bool
     {
-        (self.flags & 0x00000100) != 0
+        (self.flags & TEXTUREFLAGS_NOMIP) != 0
+    }
+
+    pub fn is_envmap(&self) -> bool
+    {
+        (self.flags & TEXTUREFLAGS_ENVMAP) != 0
     }
 
     pub fn hint_dx5(&self) -> bool
     {
-        (self.flags & 0x0020) != 0
+        (self.flags & TEXTUREFLAGS_HINT_DXT5) != 0
     }
 
     /// Get start of largest mip
     pub fn get_mip0_start(&self) -> usize
     {
-        let mut mips = 0;
         let mut lower_mip_sizes: usize = 0;
 
         let mut width: usize = (self.width >> 1) as usize;
         let mut height: usize = (self.height >> 1) as usize;
+        let mut depth: usize = self.get_depth() >> 1;
 
         while width > 0 || height > 0
         {
-            let mip_size = (width * height) * (self.get_channels() * self.depth) as usize;
+            let mip_size = (width.max(1) * height.max(1) * depth.max(1)) * self.get_channels() as usize;
             lower_mip_sizes += mip_size;
-            width = width >> 1;
-            height = height >> 1;
-            mips += 1;
+            width >>= 1;
+            height >>= 1;
+            depth >>= 1;
         }
 
-        println!("Mip count: {}. Header count: {}", mips, self.mip_count);
         lower_mip_sizes
     }
 
     ///Get start of mip 0 in compressed dxt sizing
-    pub fn get_mip0_dxt_start(&self) -> Result<usize, Box<dyn Error>>
+    pub fn get_mip0_dxt_start(&self) -> Result<usize, VtfxError>
     {
         let mip0_start = self.get_mip0_start();
-        let bc_format = self.image_format.try_get_format_info()?.try_get_bc_format()?;
+        let bc_format = self.try_get_bc_format()?;
         let bc_ratio: usize = (4 * 4 * self.get_channels() as usize) / bc_format.block_size();
 
         let dxt_offset = mip0_start / bc_ratio;
@@ -207,17 +259,77 @@ impl VTFXHEADER
     }
 
     ///Get the size of dxt data for this vtfx's mip 0
-    pub fn get_dxt_size(&self) -> Result<usize, Box<dyn Error>>
+    pub fn get_dxt_size(&self) -> Result<usize, VtfxError>
     {
         let decoded_size: usize = self.get_total_size();
-        let bc_format = self.image_format.try_get_format_info()?.try_get_bc_format()?;
+        let bc_format = self.try_get_bc_format()?;
         let bc_ratio: usize = (4 * 4 * self.get_channels() as usize) / bc_format.block_size();
         Ok(decoded_size / bc_ratio)
     }
 
+    ///Get the dxt bc format of this vtfx's image format
+    pub fn try_get_bc_format(&self) -> Result<texpresso::Format, VtfxError>
+    {
+        self.image_format.try_get_format_info()?.bc_format.ok_or(VtfxError::NotBlockCompressed(self.image_format))
+    }
+
+    ///Get number of frames, at least 1
+    pub fn get_frame_count(&self) -> usize
+    {
+        (self.num_frames as usize).max(1)
+    }
+
+    ///Get number of faces, 6 for cubemaps otherwise 1
+    pub fn get_face_count(&self) -> usize
+    {
+        match self.is_envmap() {
+            true => CUBEMAP_FACE_COUNT,
+            false => 1
+        }
+    }
+
+    ///Get number of volume slices, at least 1
+    pub fn get_depth(&self) -> usize
+    {
+        (self.depth as usize).max(1)
+    }
+
+    ///If this is a volume texture (more than 1 slice)
+    pub fn is_volume(&self) -> bool
+    {
+        self.get_depth() > 1
+    }
+
+    ///Get number of volume slices of a mip level (0 is the largest)
+    pub fn get_mip_depth(&self, level: usize) -> usize
+    {
+        self.get_depth().checked_shr(level as u32).unwrap_or(0).max(1)
+    }
+
+    ///Get dimensions of a mip level (0 is the largest)
+    pub fn get_mip_size(&self, level: usize) -> (usize, usize)
+    {
+        let width = (self.width as usize).checked_shr(level as u32).unwrap_or(0).max(1);
+        let height = (self.height as usize).checked_shr(level as u32).unwrap_or(0).max(1);
+        (width, height)
+    }
+
+    ///If image resource mips are packed smallest to largest. Otherwise the largest mip is read from the start of the resource.
+    ///Xbox resources and dxt resources are packed smallest to largest.
+    pub fn mips_smallest_first(&self) -> bool
+    {
+        self.is_xbox() || self.try_get_bc_format().is_ok()
+    }
+
+    ///Get dimensions of the low res image. Halves the image size until it fits, same as vtex
+    pub fn get_low_res_size(&self) -> (usize, usize)
+    {
+        get_low_res_size(self.width as usize, self.height as usize)
+    }
+
     pub fn get_total_size(&self) -> usize
     {
-        self.width as usize * self.height as usize * self.get_channels() as usize
+        self.width as usize * self.height as usize * self.get_depth() * self.get_channels() as usize
     }
 
     pub fn is_xbox(&self) -> bool
@@ -226,25 +338,156 @@ impl VTFXHEADER
     }
 }
 
+///Get dimensions of the low res image of an image with this size. Halves the size until it fits, same as vtex
+pub fn get_low_res_size(width: usize, height: usize) -> (usize, usize)
+{
+    let mut width = width.max(1);
+    let mut height = height.max(1);
+    while width > LOW_RES_IMAGE_SIZE || height > LOW_RES_IMAGE_SIZE
+    {
+        width = (width >> 1).max(1);
+        height = (height >> 1).max(1);
+    }
+
+    (width, height)
+}
+
+///Read N bytes from buffer at offset, returning an error if the buffer is too short
+pub(crate) fn read_bytes<const N: usize>(buffer: &[u8], offset: usize) -> Result<[u8; N], VtfxError>
+{
+    buffer.get(offset..offset + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(VtfxError::TruncatedHeader { offset, needed: N, available: buffer.len() })
+}
+
 impl fmt::Display for VTFXHEADER {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(Version: {}.{}, Header Size: {}, Width: {}, Height: {}, Depth: {}, Num Frames: {}, Preload data size: {}, Mip count: {}, Mip skip count: {}, Bump Scale: {}, Image Format: {:?}, Compressed Size: {} | All mip: {}, No mip: {}, Has Alpha: {})",
-            self.version[0], self.version[1], self.header_size, self.width, self.height, self.depth, self.num_frames, self.preload_data_size, self.mip_count, self.mip_skip_count, self.bump_scale, self.image_format, self.compressed_size, self.all_mips(), self.no_mips(), self.has_alpha())
+        write!(f, "(Version: {}.{}, Header Size: {}, Width: {}, Height: {}, Depth: {}, Num Frames: {}, Preload data size: {}, Mip count: {}, Mip skip count: {}, Bump Scale: {}, Image Format: {:?}, Compressed Size: {}, Low Res Sample: {:?} | All mip: {}, No mip: {}, Has Alpha: {}, Envmap: {})",
+            self.version[0], self.version[1], self.header_size, self.width, self.height, self.depth, self.num_frames, self.preload_data_size, self.mip_count, self.mip_skip_count, self.bump_scale, self.image_format, self.compressed_size, self.low_res_image_sample, self.all_mips(), self.no_mips(), self.has_alpha(), self.is_envmap())
     }
 }
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Vector {
-    x: f32,
-    y: f32,
-    z: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
 }
 
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Rect
 {
-    x: f32,
-    y: f32,
-    width: f32,
-    height: f32
-}
\ No newline at end of file
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32
+}
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::test_util::{FixtureData, VtfxFixture};
+
+    ///Build a 360 vtfx header with a resource table of (type bytes, resData)
+    fn build_header(width: u16, height: u16, image_format: i32, resources: &[([u8; 4], u32)]) -> Vec<u8>
+    {
+        VtfxFixture {
+            flags: TEXTUREFLAGS_EIGHTBITALPHA | TEXTUREFLAGS_NOMIP,
+            width,
+            height,
+            frames: 3,
+            reflectivity: [0.25, 0.5, 0.75],
+            image_format,
+            low_res_image_sample: [1, 2, 3, 4],
+            resources: resources.iter().map(|&(type_bytes, res_data)| (type_bytes, FixtureData::Value(res_data))).collect(),
+            ..Default::default()
+        }.build()
+    }
+
+    #[test]
+    fn header_is_parsed()
+    {
+        let buffer = build_header(64, 32, ImageFormat::IMAGE_FORMAT_DXT5 as i32, &[]);
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        assert!(vtfx.is_xbox());
+        assert_eq!((vtfx.width, vtfx.height, vtfx.depth, vtfx.num_frames), (64, 32, 1, 3));
+        assert_eq!(vtfx.image_format, ImageFormat::IMAGE_FORMAT_DXT5);
+        assert_eq!((vtfx.reflectivity.x, vtfx.reflectivity.y, vtfx.reflectivity.z, vtfx.bump_scale), (0.25, 0.5, 0.75, 1.0));
+        assert_eq!(vtfx.low_res_image_sample, [1, 2, 3, 4]);
+        assert!(vtfx.no_mips() && vtfx.has_alpha());
+        assert_eq!(vtfx.mip_count, 1);
+    }
+
+    #[test]
+    fn bad_magic_is_rejected()
+    {
+        let mut buffer = build_header(4, 4, ImageFormat::IMAGE_FORMAT_DXT1 as i32, &[]);
+        buffer[0..4].copy_from_slice(b"VTF\0");
+        assert!(matches!(VTFXHEADER::from(&buffer), Err(VtfxError::BadMagic { found }) if &found == b"VTF\0"));
+    }
+
+    #[test]
+    fn unknown_version_is_rejected()
+    {
+        let mut buffer = build_header(4, 4, ImageFormat::IMAGE_FORMAT_DXT1 as i32, &[]);
+        buffer[4..8].copy_from_slice(&7i32.to_be_bytes());
+        assert!(matches!(VTFXHEADER::from(&buffer), Err(VtfxError::UnsupportedVersion { major: 7, minor: 8 })));
+    }
+
+    #[test]
+    fn unknown_image_format_is_rejected()
+    {
+        let buffer = build_header(4, 4, 1000, &[]);
+        assert!(matches!(VTFXHEADER::from(&buffer), Err(VtfxError::UnknownImageFormat { id: 1000, offset: 48 })));
+    }
+
+    #[test]
+    fn truncated_header_is_rejected()
+    {
+        let buffer = build_header(4, 4, ImageFormat::IMAGE_FORMAT_DXT1 as i32, &[]);
+        for length in [0, 3, 20, 47, RESOURCE_START - 1]
+        {
+            let result = VTFXHEADER::from(&buffer[..length]);
+            assert!(matches!(result, Err(VtfxError::TruncatedHeader { available, .. }) if available == length), "length {}: {:?}", length, result);
+        }
+    }
+
+    #[test]
+    fn truncated_resource_table_is_rejected()
+    {
+        let buffer = build_header(4, 4, ImageFormat::IMAGE_FORMAT_DXT1 as i32, &[(VTF_LEGACY_RSRC_IMAGE, 0), (VTF_LEGACY_RSRC_LOW_RES_IMAGE, 0)]);
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        let result = vtfx.get_resource_entry_infos(&buffer[..buffer.len() - 1]);
+        assert!(matches!(result, Err(VtfxError::TruncatedHeader { offset: RESOURCE_START, needed: 16, .. })), "{:?}", result);
+    }
+
+    #[test]
+    fn resource_outside_of_file_is_rejected()
+    {
+        let mut buffer = build_header(4, 4, ImageFormat::IMAGE_FORMAT_DXT1 as i32, &[(VTF_LEGACY_RSRC_LOW_RES_IMAGE, 76), (VTF_LEGACY_RSRC_IMAGE, 200)]);
+        buffer.extend_from_slice(&[0; 16]);
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        let result = vtfx.get_resource_entry_infos(&buffer);
+        assert!(matches!(result, Err(VtfxError::ResourceOutOfBounds { resource: 1, offset: 200, file_size: 92 })), "{:?}", result);
+    }
+
+    #[test]
+    fn resource_without_data_chunk_is_not_an_offset()
+    {
+        let buffer = build_header(4, 4, ImageFormat::IMAGE_FORMAT_DXT1 as i32, &[([b'C', b'R', b'C', RSRCF_HAS_NO_DATA_CHUNK], 0xDEADBEEF)]);
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        let resources = vtfx.get_resource_entry_infos(&buffer).unwrap();
+        assert_eq!(resources.len(), 1);
+        assert_eq!(resources[0].resData, 0xDEADBEEF);
+    }
+
+    #[test]
+    fn low_res_size_fits_within_16()
+    {
+        assert_eq!(get_low_res_size(512, 256), (16, 8));
+        assert_eq!(get_low_res_size(1024, 32), (16, 1));
+        assert_eq!(get_low_res_size(8, 4), (8, 4));
+        assert_eq!(get_low_res_size(0, 0), (1, 1));
+    }
+}
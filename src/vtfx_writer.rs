@@ -0,0 +1,682 @@
+//Writes console (big endian) vtfx files from PC vtf files or images, the reverse of VTFXHEADER::from and vtf_writer
+//https://developer.valvesoftware.com/wiki/VTFX_file_format
+
+use std::io::Write;
+
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, RgbaImage};
+use num_enum::TryFromPrimitive;
+
+use crate::decode::{decode_bc, get_block_layout};
+use crate::error::VtfxError;
+use crate::image_format::{correct_endianness, image_format_info, GetNumMipMapLevels, ImageFormat};
+use crate::swizzle::{can_swizzle, swizzle_ps3};
+use crate::untile::{can_tile, get_mip_tail_start, get_packed_mip_offset, get_tiled_size, tile_x360, tile_x360_region};
+use crate::vtf_writer::{get_chunk_length_size, VtfResource, NO_SPHERE_MAP_START_FRAME, VTF_HEADER_SIZE, VTF_MAJOR_VERSION, VTF_MAX_MINOR_VERSION, VTF_MINOR_VERSION_MIN_NO_SPHERE_MAP, VTF_MINOR_VERSION_MIN_RESOURCE};
+use crate::vtfx::{get_low_res_size, read_bytes, Vector, CUBEMAP_FACE_COUNT, LOW_RES_IMAGE_FORMAT, RESOURCE_ENTRY_SIZE, RESOURCE_START, RSRCF_HAS_NO_DATA_CHUNK, TEXTUREFLAGS_EIGHTBITALPHA, TEXTUREFLAGS_ENVMAP, TEXTUREFLAGS_NOMIP, TEXTUREFLAGS_ONEBITALPHA, VTF_LEGACY_RSRC_IMAGE, VTF_LEGACY_RSRC_LOW_RES_IMAGE, VTF_PS3_MAJOR_VERSION, VTF_PS3_MINOR_VERSION, VTF_RSRC_KEY_VALUE_DATA, VTF_RSRC_SHEET, VTF_X360_MAJOR_VERSION, VTF_X360_MINOR_VERSION};
+
+///Versions from 7.2 have a depth
+const VTF_MINOR_VERSION_MIN_DEPTH: u32 = 2;
+///Spheremap face that cubemaps before 7.5 may have
+const SPHERE_MAP_FACE_COUNT: usize = 1;
+
+///Console platform to write a vtfx for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VtfxPlatform
+{
+    ///Xbox 360 (v 864.8), dxt data is byte swapped
+    Xbox360,
+    ///PS3 (v 819.8)
+    Ps3,
+}
+
+impl VtfxPlatform
+{
+    pub fn get_version(&self) -> [i32; 2]
+    {
+        match self
+        {
+            VtfxPlatform::Xbox360 => [VTF_X360_MAJOR_VERSION, VTF_X360_MINOR_VERSION],
+            VtfxPlatform::Ps3 => [VTF_PS3_MAJOR_VERSION, VTF_PS3_MINOR_VERSION],
+        }
+    }
+}
+
+///A texture in PC vtf layout and byte order (little endian), to be written as a vtfx
+#[derive(Debug, Clone)]
+pub struct PcTexture
+{
+    pub flags: u32,
+    pub width: u16,
+    pub height: u16,
+    pub depth: u16,
+    pub frames: u16,
+    pub reflectivity: Vector,
+    pub bump_scale: f32,
+    pub image_format: ImageFormat,
+    pub mip_count: usize,
+    ///Number of cubemap faces (including the spheremap face if present), otherwise 1
+    pub face_count: usize,
+    ///Mips are stored smallest to largest, each mip has all frames, each frame all faces and each face all slices
+    pub image_data: Vec<u8>,
+    ///Low res DXT1 image
+    pub low_res_data: Option<Vec<u8>>,
+    ///Resources other than the images, as stored in a PC vtf
+    pub resources: Vec<VtfResource>,
+}
+
+impl PcTexture
+{
+    ///Read a PC vtf (7.0 to 7.5). Image data is copied without decoding.
+    ///Resources of unknown type are not kept as their byte order is unknown.
+    pub fn from_vtf(buffer: &[u8]) -> Result<PcTexture, VtfxError>
+    {
+        let magic: [u8; 4] = read_bytes(buffer, 0)?;
+        if &magic != b"VTF\0"
+        {
+            return Err(VtfxError::BadVtfMagic { found: magic });
+        }
+
+        let major_version = u32::from_le_bytes(read_bytes(buffer, 4)?);
+        let minor_version = u32::from_le_bytes(read_bytes(buffer, 8)?);
+        if major_version != VTF_MAJOR_VERSION || minor_version > VTF_MAX_MINOR_VERSION
+        {
+            return Err(VtfxError::UnsupportedVtfVersion { major: major_version, minor: minor_version });
+        }
+
+        let header_size = u32::from_le_bytes(read_bytes(buffer, 12)?) as usize;
+        let width = u16::from_le_bytes(read_bytes(buffer, 16)?);
+        let height = u16::from_le_bytes(read_bytes(buffer, 18)?);
+        let flags = u32::from_le_bytes(read_bytes(buffer, 20)?);
+        let frames = u16::from_le_bytes(read_bytes(buffer, 24)?).max(1);
+        let start_frame = u16::from_le_bytes(read_bytes(buffer, 26)?);
+        let reflectivity = Vector {
+            x: f32::from_le_bytes(read_bytes(buffer, 32)?),
+            y: f32::from_le_bytes(read_bytes(buffer, 36)?),
+            z: f32::from_le_bytes(read_bytes(buffer, 40)?),
+        };
+        let bump_scale = f32::from_le_bytes(read_bytes(buffer, 48)?);
+        let image_format_i32 = i32::from_le_bytes(read_bytes(buffer, 52)?);
+        let image_format = ImageFormat::try_from_primitive(image_format_i32)
+            .map_err(|_| VtfxError::UnknownImageFormat { id: image_format_i32, offset: 52 })?;
+        let mip_count = u8::from_le_bytes(read_bytes(buffer, 56)?).max(1) as usize;
+        let low_res_format = i32::from_le_bytes(read_bytes(buffer, 57)?);
+        let [low_res_width, low_res_height] = read_bytes::<2>(buffer, 61)?;
+        let depth = match minor_version >= VTF_MINOR_VERSION_MIN_DEPTH
+        {
+            true => u16::from_le_bytes(read_bytes(buffer, 63)?).max(1),
+            false => 1
+        };
+
+        //Cubemaps before 7.5 have a spheremap face unless the start frame says otherwise
+        let face_count = match (flags & TEXTUREFLAGS_ENVMAP) != 0
+        {
+            true if minor_version < VTF_MINOR_VERSION_MIN_NO_SPHERE_MAP && start_frame != NO_SPHERE_MAP_START_FRAME => CUBEMAP_FACE_COUNT + SPHERE_MAP_FACE_COUNT,
+            true => CUBEMAP_FACE_COUNT,
+            false => 1
+        };
+
+        let mut texture = PcTexture {
+            flags,
+            width,
+            height,
+            depth,
+            frames,
+            reflectivity,
+            bump_scale,
+            image_format,
+            mip_count,
+            face_count,
+            image_data: Vec::new(),
+            low_res_data: None,
+            resources: Vec::new(),
+        };
+
+        //The vtfx low res size is not stored, so it must be the size vtex would use
+        let low_res_dimensions = (low_res_width as usize, low_res_height as usize);
+        let low_res_size = match low_res_format == LOW_RES_IMAGE_FORMAT as i32 && low_res_dimensions == get_low_res_size(width as usize, height as usize)
+        {
+            true => LOW_RES_IMAGE_FORMAT.try_get_format_info()?.get_expected_size(&(low_res_width as usize), &(low_res_height as usize)),
+            false => 0
+        };
+        let image_size = texture.get_image_size()?;
+
+        //Versions before 7.3 have no resource table, the low res image is followed by the image
+        let mut low_res_offset = Some(header_size).filter(|_| low_res_size > 0);
+        let mut image_offset = header_size + low_res_size;
+        if minor_version >= VTF_MINOR_VERSION_MIN_RESOURCE
+        {
+            low_res_offset = None;
+            let num_resources = u32::from_le_bytes(read_bytes(buffer, 68)?) as usize;
+            for res_num in 0..num_resources
+            {
+                let entry = VTF_HEADER_SIZE + res_num * RESOURCE_ENTRY_SIZE;
+                let type_bytes: [u8; 4] = read_bytes(buffer, entry)?;
+                let res_data = u32::from_le_bytes(read_bytes(buffer, entry + 4)?);
+                if (type_bytes[3] & RSRCF_HAS_NO_DATA_CHUNK) != 0
+                {
+                    texture.resources.push(VtfResource::Value { type_bytes, value: res_data });
+                    continue;
+                }
+
+                let offset = res_data as usize;
+                match type_bytes
+                {
+                    VTF_LEGACY_RSRC_IMAGE => image_offset = offset,
+                    VTF_LEGACY_RSRC_LOW_RES_IMAGE => low_res_offset = Some(offset).filter(|_| low_res_size > 0),
+                    VTF_RSRC_SHEET | VTF_RSRC_KEY_VALUE_DATA => {
+                        let length = u32::from_le_bytes(read_bytes(buffer, offset)?) as usize;
+                        let data = read_chunk(buffer, offset + 4, length, res_num)?;
+                        texture.resources.push(VtfResource::Data { type_bytes, data });
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        texture.image_data = read_chunk(buffer, image_offset, image_size, 0)?;
+        if let Some(low_res_offset) = low_res_offset
+        {
+            texture.low_res_data = Some(read_chunk(buffer, low_res_offset, low_res_size, 0)?);
+        }
+
+        Ok(texture)
+    }
+
+    ///Encode an image as image_format (8 bit RGBA/BGRA/RGB/BGR formats or DXT1/3/5), with a full mip chain if mips is enabled.
+    ///The low res image, reflectivity and alpha flags are generated the same way as vtex.
+    pub fn from_image(image: &DynamicImage, image_format: ImageFormat, mips: bool) -> Result<PcTexture, VtfxError>
+    {
+        let image = image.to_rgba8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mip_count = match mips
+        {
+            true => GetNumMipMapLevels(width as i32, height as i32, 1) as usize,
+            false => 1
+        };
+
+        let mut mip_images = vec![image.clone()];
+        for level in 1..mip_count
+        {
+            let mip_width = (width >> level).max(1) as u32;
+            let mip_height = (height >> level).max(1) as u32;
+            mip_images.push(imageops::resize(&image, mip_width, mip_height, FilterType::Triangle));
+        }
+
+        let mut image_data = Vec::new();
+        for mip_image in mip_images.iter().rev()
+        {
+            image_data.extend_from_slice(&encode_rgba(mip_image, image_format)?);
+        }
+
+        let (low_res_width, low_res_height) = get_low_res_size(width, height);
+        let low_res_image = imageops::resize(&image, low_res_width as u32, low_res_height as u32, FilterType::Triangle);
+        let low_res_data = encode_rgba(&low_res_image, LOW_RES_IMAGE_FORMAT)?;
+
+        let mut flags = match image.pixels().any(|pixel| pixel[3] < 255)
+        {
+            true => match image_format
+            {
+                ImageFormat::IMAGE_FORMAT_DXT1 => TEXTUREFLAGS_ONEBITALPHA,
+                ImageFormat::IMAGE_FORMAT_BGRX8888 | ImageFormat::IMAGE_FORMAT_RGB888 | ImageFormat::IMAGE_FORMAT_BGR888 => 0,
+                _ => TEXTUREFLAGS_EIGHTBITALPHA
+            },
+            false => 0
+        };
+        if !mips
+        {
+            flags |= TEXTUREFLAGS_NOMIP;
+        }
+
+        Ok(PcTexture {
+            flags,
+            width: width as u16,
+            height: height as u16,
+            depth: 1,
+            frames: 1,
+            reflectivity: get_reflectivity(&image),
+            bump_scale: 1.0,
+            image_format,
+            mip_count,
+            face_count: 1,
+            image_data,
+            low_res_data: Some(low_res_data),
+            resources: Vec::new(),
+        })
+    }
+
+    ///Get width and height of a mip level (0 is the largest)
+    pub fn get_mip_size(&self, level: usize) -> (usize, usize)
+    {
+        let width = (self.width as usize).checked_shr(level as u32).unwrap_or(0).max(1);
+        let height = (self.height as usize).checked_shr(level as u32).unwrap_or(0).max(1);
+        (width, height)
+    }
+
+    ///Get number of volume slices of a mip level
+    pub fn get_mip_depth(&self, level: usize) -> usize
+    {
+        (self.depth as usize).checked_shr(level as u32).unwrap_or(0).max(1)
+    }
+
+    ///Get size of the image data of a mip level (0 is the largest), including all frames, faces and slices
+    pub fn get_mip_data_size(&self, level: usize) -> Result<usize, VtfxError>
+    {
+        let format_info = self.image_format.try_get_format_info()?;
+        let (width, height) = self.get_mip_size(level);
+        Ok(format_info.get_expected_size(&width, &height) * self.get_mip_depth(level) * self.face_count * self.frames.max(1) as usize)
+    }
+
+    ///Get size of the image data of all mip levels
+    pub fn get_image_size(&self) -> Result<usize, VtfxError>
+    {
+        (0..self.mip_count).map(|level| self.get_mip_data_size(level)).sum()
+    }
+
+    ///Get the data of a single volume slice of a face of a frame of a mip level
+    pub fn get_mip_slice_data(&self, level: usize, frame: usize, face: usize, slice: usize) -> Result<&[u8], VtfxError>
+    {
+        let format_info = self.image_format.try_get_format_info()?;
+        let (width, height) = self.get_mip_size(level);
+        let slice_size = format_info.get_expected_size(&width, &height);
+        //Mips are stored smallest to largest
+        let mip_start = (level + 1..self.mip_count).map(|smaller| self.get_mip_data_size(smaller)).sum::<Result<usize, VtfxError>>()?;
+        let start = mip_start + ((((frame * self.face_count) + face) * self.get_mip_depth(level)) + slice) * slice_size;
+        self.image_data.get(start..start + slice_size)
+            .ok_or(VtfxError::SizeMismatch { expected: start + slice_size, actual: self.image_data.len() })
+    }
+}
+
+///Write a texture as a vtfx for platform. Data is converted to the platform's byte order, mip order and layout (see [`get_console_slice_data`]),
+///and is not compressed (compressed size is 0). Cubemaps are written without a spheremap face.
+pub fn write_vtfx<W: Write>(texture: &PcTexture, platform: VtfxPlatform, mut writer: W) -> Result<(), VtfxError>
+{
+    let full_mip_count = GetNumMipMapLevels(texture.width as i32, texture.height as i32, texture.depth as i32) as usize;
+    let flags = match texture.mip_count
+    {
+        1 => texture.flags | TEXTUREFLAGS_NOMIP,
+        mip_count if mip_count == full_mip_count => texture.flags & !TEXTUREFLAGS_NOMIP,
+        mip_count => return Err(VtfxError::PartialMipChain { mip_count, expected: full_mip_count })
+    };
+
+    let image_size = texture.get_image_size()?;
+    if texture.image_data.len() < image_size
+    {
+        return Err(VtfxError::SizeMismatch { expected: image_size, actual: texture.image_data.len() });
+    }
+
+    //Xbox and dxt resources are packed smallest to largest (same as PC), otherwise largest to smallest
+    let format_info = texture.image_format.try_get_format_info()?;
+    let fix_dxt = platform == VtfxPlatform::Xbox360;
+    let levels: Vec<usize> = match fix_dxt || format_info.bc_format.is_some()
+    {
+        true => (0..texture.mip_count).rev().collect(),
+        false => (0..texture.mip_count).collect()
+    };
+    let layout = ConsoleLayout::new(texture, platform, format_info);
+    let face_count = texture.face_count.min(CUBEMAP_FACE_COUNT);
+    let mut image_data = Vec::with_capacity(image_size);
+    for level in levels
+    {
+        for frame in 0..texture.frames.max(1) as usize
+        {
+            for face in 0..face_count
+            {
+                for slice in 0..texture.get_mip_depth(level)
+                {
+                    image_data.extend_from_slice(&get_console_slice_data(texture, &layout, level, frame, face, slice)?);
+                }
+            }
+        }
+    }
+
+    let mut low_res_image_sample = [0; 4];
+    let mut resources = Vec::new();
+    if let Some(low_res_data) = &texture.low_res_data
+    {
+        low_res_image_sample = get_low_res_sample(low_res_data, texture.width as usize, texture.height as usize)?;
+        let mut data = low_res_data.clone();
+        correct_endianness(LOW_RES_IMAGE_FORMAT, &mut data, fix_dxt)?;
+        resources.push(VtfResource::Data { type_bytes: VTF_LEGACY_RSRC_LOW_RES_IMAGE, data });
+    }
+    resources.push(VtfResource::Data { type_bytes: VTF_LEGACY_RSRC_IMAGE, data: image_data });
+
+    for resource in &texture.resources
+    {
+        match resource
+        {
+            VtfResource::Value { .. } => resources.push(resource.clone()),
+            //Sheets are only i32 and f32 values, so every 4 bytes are swapped
+            VtfResource::Data { type_bytes: VTF_RSRC_SHEET, data } => {
+                let mut data = data.clone();
+                data.chunks_exact_mut(4).for_each(|value| value.reverse());
+                resources.push(VtfResource::Data { type_bytes: VTF_RSRC_SHEET, data });
+            },
+            VtfResource::Data { type_bytes: VTF_RSRC_KEY_VALUE_DATA, .. } => resources.push(resource.clone()),
+            VtfResource::Data { .. } => {}
+        }
+    }
+    resources.sort_by_key(|resource| match resource
+    {
+        VtfResource::Value { type_bytes, .. } | VtfResource::Data { type_bytes, .. } => *type_bytes
+    });
+
+    let header_size = RESOURCE_START + resources.len() * RESOURCE_ENTRY_SIZE;
+
+    //Resource table, data chunks follow in the same order. Images are not prefixed by their size.
+    let mut table = Vec::with_capacity(resources.len() * RESOURCE_ENTRY_SIZE);
+    let mut offset = header_size;
+    let mut preload_data_size = header_size;
+    for resource in &resources
+    {
+        match resource
+        {
+            VtfResource::Value { type_bytes, value } => {
+                table.extend_from_slice(type_bytes);
+                table.extend_from_slice(&value.to_be_bytes());
+            },
+            VtfResource::Data { type_bytes, data } => {
+                table.extend_from_slice(type_bytes);
+                table.extend_from_slice(&(offset as u32).to_be_bytes());
+                //Preload data is everything before the image
+                if *type_bytes == VTF_LEGACY_RSRC_IMAGE
+                {
+                    preload_data_size = offset;
+                }
+                offset += data.len() + get_chunk_length_size(type_bytes);
+            }
+        }
+    }
+
+    let version = platform.get_version();
+    let mut output = Vec::with_capacity(offset);
+    output.extend_from_slice(b"VTFX");
+    output.extend_from_slice(&version[0].to_be_bytes());
+    output.extend_from_slice(&version[1].to_be_bytes());
+    output.extend_from_slice(&(header_size as i32).to_be_bytes());
+    output.extend_from_slice(&flags.to_be_bytes());
+    output.extend_from_slice(&texture.width.to_be_bytes());
+    output.extend_from_slice(&texture.height.to_be_bytes());
+    output.extend_from_slice(&texture.depth.max(1).to_be_bytes());
+    output.extend_from_slice(&texture.frames.max(1).to_be_bytes());
+    output.extend_from_slice(&(preload_data_size.min(u16::MAX as usize) as u16).to_be_bytes());
+    output.push(0);
+    output.push(resources.len() as u8);
+    output.extend_from_slice(&texture.reflectivity.x.to_be_bytes());
+    output.extend_from_slice(&texture.reflectivity.y.to_be_bytes());
+    output.extend_from_slice(&texture.reflectivity.z.to_be_bytes());
+    output.extend_from_slice(&texture.bump_scale.to_be_bytes());
+    output.extend_from_slice(&(texture.image_format as i32).to_be_bytes());
+    output.extend_from_slice(&low_res_image_sample);
+    //Resources are not lzma compressed
+    output.extend_from_slice(&0u32.to_be_bytes());
+    output.extend_from_slice(&table);
+
+    for resource in &resources
+    {
+        if let VtfResource::Data { type_bytes, data } = resource
+        {
+            if get_chunk_length_size(type_bytes) > 0
+            {
+                output.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            }
+            output.extend_from_slice(data);
+        }
+    }
+
+    writer.write_all(&output)?;
+
+    Ok(())
+}
+
+///How image data is laid out on a console, which is the same as the vtfx reader expects by default
+struct ConsoleLayout<'a>
+{
+    format_info: &'a image_format_info,
+    fix_dxt: bool,
+    ///Xbox 360 surfaces are tiled unless they are in a LINEAR format, have 24 bit pixels or are volume textures
+    tile: bool,
+    ///First tiled mip level packed into the mip tail
+    mip_tail_start: Option<usize>,
+    ///Uncompressed ps3 surfaces are swizzled unless they are in a LINEAR format, are not a power of 2 in size or are volume textures
+    swizzle: bool,
+}
+
+impl<'a> ConsoleLayout<'a>
+{
+    fn new(texture: &PcTexture, platform: VtfxPlatform, format_info: &'a image_format_info) -> ConsoleLayout<'a>
+    {
+        let (_, _, block_size) = get_block_layout(1, 1, format_info);
+        let is_linear = texture.image_format.is_linear();
+        let is_volume = texture.depth > 1;
+        let tile = platform == VtfxPlatform::Xbox360 && !is_linear && can_tile(block_size) && !is_volume;
+        ConsoleLayout {
+            format_info,
+            fix_dxt: platform == VtfxPlatform::Xbox360,
+            tile,
+            mip_tail_start: get_mip_tail_start(texture.width as usize, texture.height as usize, texture.mip_count).filter(|_| tile),
+            swizzle: platform == VtfxPlatform::Ps3 && !is_linear && format_info.bc_format.is_none() && can_swizzle(texture.width as usize, texture.height as usize) && !is_volume,
+        }
+    }
+}
+
+///Get a slice of a face of a frame of a mip level in the platform's byte order, tiled or swizzled if needed.
+///Tiled mips in the mip tail are all written in the surface of the first packed mip, so later packed mips have no data of their own.
+fn get_console_slice_data(texture: &PcTexture, layout: &ConsoleLayout, level: usize, frame: usize, face: usize, slice: usize) -> Result<Vec<u8>, VtfxError>
+{
+    let get_data = |level: usize| -> Result<Vec<u8>, VtfxError> {
+        let mut data = texture.get_mip_slice_data(level, frame, face, slice)?.to_vec();
+        correct_endianness(texture.image_format, &mut data, layout.fix_dxt)?;
+        Ok(data)
+    };
+
+    let (width, height) = texture.get_mip_size(level);
+    let (block_width, block_height, block_size) = get_block_layout(width, height, layout.format_info);
+    if layout.tile
+    {
+        return match layout.mip_tail_start
+        {
+            Some(tail_start) if level > tail_start => Ok(Vec::new()),
+            Some(tail_start) if level == tail_start => {
+                let block_dimension = match layout.format_info.bc_format
+                {
+                    Some(_) => 4,
+                    None => 1
+                };
+                let mut surface = vec![0u8; get_tiled_size(block_width, block_height, block_size)];
+                for packed_level in tail_start..texture.mip_count
+                {
+                    let (packed_width, packed_height) = texture.get_mip_size(packed_level);
+                    let (packed_block_width, packed_block_height, _) = get_block_layout(packed_width, packed_height, layout.format_info);
+                    let (x, y) = get_packed_mip_offset(texture.width as usize, texture.height as usize, packed_level, block_dimension);
+                    tile_x360_region(&get_data(packed_level)?, &mut surface, block_width, x, y, packed_block_width, packed_block_height, block_size);
+                }
+                Ok(surface)
+            },
+            _ => Ok(tile_x360(&get_data(level)?, block_width, block_height, block_size))
+        };
+    }
+
+    let data = get_data(level)?;
+    match layout.swizzle
+    {
+        true => Ok(swizzle_ps3(&data, width, height, layout.format_info.get_pixel_size())),
+        false => Ok(data)
+    }
+}
+
+///Copy a data chunk of length at offset
+fn read_chunk(buffer: &[u8], offset: usize, length: usize, resource: usize) -> Result<Vec<u8>, VtfxError>
+{
+    buffer.get(offset..offset.saturating_add(length))
+        .map(Vec::from)
+        .ok_or(VtfxError::TruncatedResource { resource, offset, needed: length })
+}
+
+///Encode an RGBA image in a PC (little endian) image format
+fn encode_rgba(image: &RgbaImage, image_format: ImageFormat) -> Result<Vec<u8>, VtfxError>
+{
+    let bc_format = match image_format
+    {
+        ImageFormat::IMAGE_FORMAT_DXT1 => Some(texpresso::Format::Bc1),
+        ImageFormat::IMAGE_FORMAT_DXT3 => Some(texpresso::Format::Bc2),
+        ImageFormat::IMAGE_FORMAT_DXT5 => Some(texpresso::Format::Bc3),
+        _ => None
+    };
+    if let Some(bc_format) = bc_format
+    {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mut data = vec![0; bc_format.compressed_size(width, height)];
+        bc_format.compress(image.as_raw(), width, height, texpresso::Params::default(), &mut data);
+        return Ok(data);
+    }
+
+    //Index of the RGBA channel for each byte, 4 is always 255
+    let channels: &[usize] = match image_format
+    {
+        ImageFormat::IMAGE_FORMAT_RGBA8888 => &[0, 1, 2, 3],
+        ImageFormat::IMAGE_FORMAT_ABGR8888 => &[3, 2, 1, 0],
+        ImageFormat::IMAGE_FORMAT_ARGB8888 => &[3, 0, 1, 2],
+        ImageFormat::IMAGE_FORMAT_BGRA8888 => &[2, 1, 0, 3],
+        ImageFormat::IMAGE_FORMAT_BGRX8888 => &[2, 1, 0, 4],
+        ImageFormat::IMAGE_FORMAT_RGB888 => &[0, 1, 2],
+        ImageFormat::IMAGE_FORMAT_BGR888 => &[2, 1, 0],
+        _ => return Err(VtfxError::UnsupportedFormat(image_format))
+    };
+
+    Ok(image.pixels()
+        .flat_map(|pixel| channels.iter().map(|channel| pixel.0.get(*channel).copied().unwrap_or(255)))
+        .collect())
+}
+
+///Average linear colour of an image, as calculated by vtex
+fn get_reflectivity(image: &RgbaImage) -> Vector
+{
+    let pixel_count = (image.width() as usize * image.height() as usize).max(1) as f32;
+    let mut sum = [0.0f32; 3];
+    for pixel in image.pixels()
+    {
+        for (total, channel) in sum.iter_mut().zip(pixel.0)
+        {
+            *total += (channel as f32 / 255.0).powf(2.2);
+        }
+    }
+
+    Vector { x: sum[0] / pixel_count, y: sum[1] / pixel_count, z: sum[2] / pixel_count }
+}
+
+///Average colour of the low res (DXT1) image of an image with this size
+fn get_low_res_sample(low_res_data: &[u8], width: usize, height: usize) -> Result<[u8; 4], VtfxError>
+{
+    let (low_res_width, low_res_height) = get_low_res_size(width, height);
+    let bc_format = LOW_RES_IMAGE_FORMAT.try_get_format_info()?.bc_format.ok_or(VtfxError::NotBlockCompressed(LOW_RES_IMAGE_FORMAT))?;
+    let expected = bc_format.compressed_size(low_res_width, low_res_height);
+    if low_res_data.len() < expected
+    {
+        return Err(VtfxError::SizeMismatch { expected, actual: low_res_data.len() });
+    }
+
+    let rgba = decode_bc(bc_format, &low_res_data[..expected], low_res_width, low_res_height);
+
+    let mut sum = [0usize; 4];
+    for pixel in rgba.chunks_exact(4)
+    {
+        sum.iter_mut().zip(pixel).for_each(|(total, channel)| *total += *channel as usize);
+    }
+
+    let pixel_count = low_res_width * low_res_height;
+    Ok(sum.map(|total| (total / pixel_count) as u8))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::decode::{resource_to_mips, DecodeOptions};
+    use crate::resource_entry_info::ResourceType;
+    use crate::test_util::numbered_texture;
+    use crate::vtfx::VTFXHEADER;
+
+    ///Write texture for platform, read it back with the default options and check every slice has the same data in PC byte order
+    fn assert_round_trip(texture: &PcTexture, platform: VtfxPlatform, tiled: bool, swizzled: bool)
+    {
+        let context = format!("{:?} {:?} {}x{}", platform, texture.image_format, texture.width, texture.height);
+        let mut buffer = Vec::new();
+        write_vtfx(texture, platform, &mut buffer).unwrap();
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        let resource_entry_infos = vtfx.get_resource_entry_infos(&buffer).unwrap();
+        let image_entry_info = resource_entry_infos.iter().find(|info| info.get_type() == ResourceType::VTF_LEGACY_RSRC_IMAGE).unwrap();
+        let mips = resource_to_mips(&buffer, image_entry_info, &vtfx, &DecodeOptions::default()).unwrap();
+        assert_eq!((mips.is_tiled(), mips.is_swizzled()), (tiled, swizzled), "{}", context);
+        assert_eq!(mips.face_count(), texture.face_count.min(CUBEMAP_FACE_COUNT), "{}", context);
+        //The image is the only resource, so nothing but the expected data may follow it
+        assert_eq!(buffer.len() - image_entry_info.resData as usize, mips.get_total_size(), "{}", context);
+
+        for level in 0..texture.mip_count
+        {
+            for face in 0..mips.face_count()
+            {
+                let mut data = mips.get_mip_slice_data(level, 0, face, 0).unwrap();
+                correct_endianness(texture.image_format, &mut data, platform == VtfxPlatform::Xbox360).unwrap();
+                assert_eq!(data, texture.get_mip_slice_data(level, 0, face, 0).unwrap(), "{} mip {} face {}", context, level, face);
+            }
+        }
+    }
+
+    #[test]
+    fn written_mips_round_trip()
+    {
+        //64x32 packs every mip after the first into the mip tail, 128x128 stores mips 0 to 2 in their own surfaces
+        for (width, height) in [(64, 32), (128, 128)]
+        {
+            for image_format in [ImageFormat::IMAGE_FORMAT_BGRA8888, ImageFormat::IMAGE_FORMAT_DXT1, ImageFormat::IMAGE_FORMAT_DXT5]
+            {
+                let mip_count = GetNumMipMapLevels(width as i32, height as i32, 1) as usize;
+                let texture = numbered_texture(width, height, image_format, mip_count, 1);
+                let swizzled = image_format == ImageFormat::IMAGE_FORMAT_BGRA8888;
+                assert_round_trip(&texture, VtfxPlatform::Xbox360, true, false);
+                assert_round_trip(&texture, VtfxPlatform::Ps3, false, swizzled);
+            }
+        }
+    }
+
+    #[test]
+    fn written_layout_depends_on_format()
+    {
+        //24 bit pixels are never tiled, LINEAR formats are neither tiled nor swizzled
+        let texture = numbered_texture(32, 32, ImageFormat::IMAGE_FORMAT_RGB888, 1, 1);
+        assert_round_trip(&texture, VtfxPlatform::Xbox360, false, false);
+        assert_round_trip(&texture, VtfxPlatform::Ps3, false, true);
+
+        let texture = numbered_texture(32, 32, ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888, 1, 1);
+        assert_round_trip(&texture, VtfxPlatform::Xbox360, false, false);
+        assert_round_trip(&texture, VtfxPlatform::Ps3, false, false);
+
+        //Only power of 2 sizes are swizzled
+        let texture = numbered_texture(48, 32, ImageFormat::IMAGE_FORMAT_BGRA8888, 1, 1);
+        assert_round_trip(&texture, VtfxPlatform::Ps3, false, false);
+    }
+
+    #[test]
+    fn sphere_map_face_is_dropped()
+    {
+        let mut texture = numbered_texture(16, 16, ImageFormat::IMAGE_FORMAT_DXT1, 1, CUBEMAP_FACE_COUNT + SPHERE_MAP_FACE_COUNT);
+        texture.flags = TEXTUREFLAGS_ENVMAP;
+        assert_round_trip(&texture, VtfxPlatform::Xbox360, true, false);
+        assert_round_trip(&texture, VtfxPlatform::Ps3, false, false);
+    }
+
+    #[test]
+    fn low_res_sample_of_partial_block_rows()
+    {
+        //32x12 has a 16x6 low res image, which is not a whole number of block rows
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(32, 12, image::Rgba([255, 0, 0, 255])));
+        let texture = PcTexture::from_image(&image, ImageFormat::IMAGE_FORMAT_BGRA8888, false).unwrap();
+        assert_eq!(get_low_res_size(32, 12), (16, 6));
+
+        let mut buffer = Vec::new();
+        write_vtfx(&texture, VtfxPlatform::Ps3, &mut buffer).unwrap();
+        let vtfx = VTFXHEADER::from(&buffer).unwrap();
+        assert_eq!(vtfx.low_res_image_sample, [255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,453 @@
+//! Format conformance tests.
+//! Each format is given a small synthetic vtfx of literal bytes as they are stored on PS3 and xbox 360, decoded with the default options,
+//! and checked per platform against colours known from the bytes.
+//! Where the console layout of a format has not been confirmed by a sample or a format definition the test is marked unverified,
+//! it only records the current order so that changing it is deliberate.
+
+#[path = "../src/test_util.rs"]
+mod test_util;
+
+use image::DynamicImage;
+use test_util::{numbered_texture, FixtureData, VtfxFixture};
+use vtfx_reader::vtfx::{TEXTUREFLAGS_NOMIP, VTF_LEGACY_RSRC_IMAGE, VTF_PS3_MAJOR_VERSION, VTF_X360_MAJOR_VERSION};
+use vtfx_reader::vtfx_writer::{write_vtfx, PcTexture, VtfxPlatform};
+use vtfx_reader::{decode_image, DecodeOptions, ImageFormat, Palette, VtfxError};
+
+///Size of the largest tiled xbox 360 surface, a surface is at least 32x32 blocks and blocks are up to 16 bytes
+const TILED_SURFACE_SIZE: usize = 32 * 32 * 16;
+
+#[derive(Clone, Copy, Debug)]
+enum Platform
+{
+    Ps3,
+    X360,
+}
+
+const PLATFORMS: [Platform; 2] = [Platform::Ps3, Platform::X360];
+
+const RED: [u8; 4] = [0xFF, 0x00, 0x00, 0xFF];
+const GREEN: [u8; 4] = [0x00, 0xFF, 0x00, 0xFF];
+const BLUE: [u8; 4] = [0x00, 0x00, 0xFF, 0xFF];
+const OPAQUE_GREY: [u8; 4] = [0x11, 0x22, 0x33, 0xFF];
+///Colours of the 4 pixel rows of the 8 bit formats, every channel is different so any swap is caught
+const ROW: [[u8; 4]; 4] = [[0x11, 0x22, 0x33, 0x44], [0xFF, 0x00, 0x00, 0xFF], [0x00, 0xFF, 0x00, 0x00], [0x00, 0x00, 0xFF, 0x80]];
+///ROW without alpha
+const ROW_OPAQUE: [[u8; 4]; 4] = [OPAQUE_GREY, RED, GREEN, BLUE];
+
+///Build a vtfx with a single image resource (no mips) containing data
+fn build_vtfx(platform: Platform, format: ImageFormat, width: u16, height: u16, data: &[u8]) -> Vec<u8>
+{
+    build_vtfx_with_chunks(platform, format, width, height, data, &[])
+}
+
+///Build a vtfx with an image resource and extra resources with data chunks (type bytes and data)
+fn build_vtfx_with_chunks(platform: Platform, format: ImageFormat, width: u16, height: u16, data: &[u8], chunks: &[([u8; 4], &[u8])]) -> Vec<u8>
+{
+    let major_version = match platform
+    {
+        Platform::Ps3 => VTF_PS3_MAJOR_VERSION,
+        Platform::X360 => VTF_X360_MAJOR_VERSION,
+    };
+    let mut resources: Vec<([u8; 4], FixtureData)> = chunks.iter().map(|(type_bytes, chunk)| (*type_bytes, FixtureData::Chunk(chunk.to_vec()))).collect();
+    resources.push((VTF_LEGACY_RSRC_IMAGE, FixtureData::Data(data.to_vec())));
+
+    let mut buffer = VtfxFixture { major_version, flags: TEXTUREFLAGS_NOMIP, width, height, image_format: format as i32, resources, ..Default::default() }.build();
+    //A row of up to 4 blocks is at the same offsets tiled and linear, but the whole tiled surface must be present
+    if let Platform::X360 = platform
+    {
+        let image_offset = buffer.len() - data.len();
+        buffer.resize(buffer.len().max(image_offset + TILED_SURFACE_SIZE), 0);
+    }
+    buffer
+}
+
+fn decode(platform: Platform, format: ImageFormat, width: u16, height: u16, data: &[u8]) -> DynamicImage
+{
+    let buffer = build_vtfx(platform, format, width, height, data);
+    decode_image(&buffer, &DecodeOptions { export_alpha: true, ..Default::default() })
+        .unwrap_or_else(|error| panic!("{:?} {:?} failed to decode: {}", platform, format, error))
+}
+
+fn assert_rgba8(image: &DynamicImage, expected: &[[u8; 4]], context: &str)
+{
+    let rgba = image.as_rgba8().unwrap_or_else(|| panic!("{}: expected Rgba8 image, got {:?}", context, image.color()));
+    let actual: Vec<[u8; 4]> = rgba.pixels().map(|pixel| pixel.0).collect();
+    assert_eq!(actual, expected, "{}", context);
+}
+
+#[test]
+fn bgra8888_is_a_big_endian_u32()
+{
+    //D3DFMT_A8R8G8B8 is stored as a big endian u32 on PS3 and xbox 360, so the bytes are A,R,G,B
+    let argb: [u8; 16] = [0x44, 0x11, 0x22, 0x33, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x80, 0x00, 0x00, 0xFF];
+    for platform in PLATFORMS
+    {
+        for format in [ImageFormat::IMAGE_FORMAT_BGRA8888, ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888]
+        {
+            let image = decode(platform, format, 4, 1, &argb);
+            assert_rgba8(&image, &ROW, &format!("{:?} {:?}", platform, format));
+        }
+    }
+}
+
+#[test]
+fn unverified_four_byte_formats_keep_current_order()
+{
+    //Unverified: no console sample or format definition confirms these. X is written as 255.
+    //ARGB8888 is read as A,B,G,R and the others are read in PC order, the same on PS3 and xbox 360
+    let rgba: [u8; 16] = [0x11, 0x22, 0x33, 0x44, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x80];
+    let abgr: [u8; 16] = [0x44, 0x33, 0x22, 0x11, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x80, 0xFF, 0x00, 0x00];
+    let bgrx: [u8; 16] = [0x33, 0x22, 0x11, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF];
+    let formats = [
+        (ImageFormat::IMAGE_FORMAT_RGBA8888, rgba, ROW),
+        (ImageFormat::IMAGE_FORMAT_ABGR8888, abgr, ROW),
+        (ImageFormat::IMAGE_FORMAT_ARGB8888, abgr, ROW),
+        (ImageFormat::IMAGE_FORMAT_BGRX8888, bgrx, ROW_OPAQUE),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_BGRX8888, bgrx, ROW_OPAQUE),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_RGBA8888, rgba, ROW),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_ABGR8888, abgr, ROW),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_ARGB8888, abgr, ROW),
+    ];
+
+    for platform in PLATFORMS
+    {
+        for (format, data, expected) in formats
+        {
+            let image = decode(platform, format, 4, 1, &data);
+            assert_rgba8(&image, &expected, &format!("{:?} {:?}", platform, format));
+        }
+    }
+}
+
+#[test]
+fn little_endian_formats_keep_pc_order()
+{
+    let formats = [
+        (ImageFormat::IMAGE_FORMAT_LE_BGRX8888, [0x33, 0x22, 0x11, 0xFF, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0xFF], ROW_OPAQUE),
+        (ImageFormat::IMAGE_FORMAT_LE_BGRA8888, [0x33, 0x22, 0x11, 0x44, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x80], ROW),
+    ];
+
+    for platform in PLATFORMS
+    {
+        for (format, data, expected) in formats
+        {
+            let image = decode(platform, format, 4, 1, &data);
+            assert_rgba8(&image, &expected, &format!("{:?} {:?}", platform, format));
+        }
+    }
+}
+
+#[test]
+fn three_byte_formats_keep_pc_order()
+{
+    let rgb: [u8; 12] = [0x11, 0x22, 0x33, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF];
+    let bgr: [u8; 12] = [0x33, 0x22, 0x11, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00];
+    let formats = [
+        (ImageFormat::IMAGE_FORMAT_RGB888, rgb),
+        (ImageFormat::IMAGE_FORMAT_BGR888, bgr),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_RGB888, rgb),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_BGR888, bgr),
+    ];
+
+    for platform in PLATFORMS
+    {
+        for (format, data) in formats
+        {
+            let image = decode(platform, format, 4, 1, &data);
+            assert_rgba8(&image, &ROW_OPAQUE, &format!("{:?} {:?}", platform, format));
+        }
+    }
+}
+
+#[test]
+fn bluescreen_formats_key_blue_to_transparent()
+{
+    //Grey, the blue key and almost blue, only pure blue is the key
+    let formats = [
+        (ImageFormat::IMAGE_FORMAT_RGB888_BLUESCREEN, [0x11, 0x22, 0x33, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFE]),
+        (ImageFormat::IMAGE_FORMAT_BGR888_BLUESCREEN, [0x33, 0x22, 0x11, 0xFF, 0x00, 0x00, 0xFE, 0x00, 0x00]),
+    ];
+    const ALMOST_BLUE: [u8; 4] = [0x00, 0x00, 0xFE, 0xFF];
+
+    for platform in PLATFORMS
+    {
+        for (format, data) in formats
+        {
+            let buffer = build_vtfx(platform, format, 3, 1, &data);
+            let context = format!("{:?} {:?}", platform, format);
+
+            let image = decode_image(&buffer, &DecodeOptions::default()).unwrap();
+            assert_rgba8(&image, &[OPAQUE_GREY, [0; 4], ALMOST_BLUE], &context);
+
+            let image = decode_image(&buffer, &DecodeOptions { keep_bluescreen: true, ..Default::default() }).unwrap();
+            assert_rgba8(&image, &[OPAQUE_GREY, BLUE, ALMOST_BLUE], &context);
+        }
+    }
+}
+
+#[test]
+fn signed_formats_are_remapped()
+{
+    //-128, -1, 0 and 127 as U,V,W,Q (or U,V,L,X), stored as a big endian u32 so reversed
+    let data: [u8; 4] = [0x7F, 0x00, 0xFF, 0x80];
+    let formats = [
+        (ImageFormat::IMAGE_FORMAT_UVWQ8888, [0x00, 0x7F, 0x80, 0xFF]),
+        //L and X are unsigned
+        (ImageFormat::IMAGE_FORMAT_UVLX8888, [0x00, 0x7F, 0x00, 0x7F]),
+    ];
+
+    for platform in PLATFORMS
+    {
+        for (format, remapped) in formats
+        {
+            let buffer = build_vtfx(platform, format, 1, 1, &data);
+            let context = format!("{:?} {:?}", platform, format);
+
+            let image = decode_image(&buffer, &DecodeOptions { export_alpha: true, ..Default::default() }).unwrap();
+            assert_rgba8(&image, &[remapped], &context);
+
+            let image = decode_image(&buffer, &DecodeOptions { export_alpha: true, raw_signed: true, ..Default::default() }).unwrap();
+            assert_rgba8(&image, &[[0x80, 0xFF, 0x00, 0x7F]], &context);
+        }
+    }
+}
+
+#[test]
+fn p8_is_expanded_with_its_palette()
+{
+    //Palette of RGBA colours where index i is (i, 255 - i, i / 2, 255)
+    let palette_data: Vec<u8> = (0..=255u8).flat_map(|index| [index, 255 - index, index / 2, 255]).collect();
+    let indices = [0, 1, 128, 255];
+    let expected = [[0x00, 0xFF, 0x00, 0xFF], [0x01, 0xFE, 0x00, 0xFF], [0x80, 0x7F, 0x40, 0xFF], [0xFF, 0x00, 0x7F, 0xFF]];
+
+    for platform in PLATFORMS
+    {
+        let context = format!("{:?} P8", platform);
+
+        //No palette in the options
+        let buffer = build_vtfx(platform, ImageFormat::IMAGE_FORMAT_P8, 4, 1, &indices);
+        assert!(matches!(decode_image(&buffer, &DecodeOptions::default()), Err(VtfxError::NoPalette)), "{}", context);
+
+        let options = DecodeOptions { palette: Some(Palette::from_bytes(&palette_data).unwrap()), ..Default::default() };
+        assert_rgba8(&decode_image(&buffer, &options).unwrap(), &expected, &context);
+
+        //A palette sized resource of unknown type is not guessed to be the palette
+        let buffer = build_vtfx_with_chunks(platform, ImageFormat::IMAGE_FORMAT_P8, 4, 1, &indices, &[([b'P', b'A', b'L', 0], &palette_data)]);
+        assert!(matches!(decode_image(&buffer, &DecodeOptions::default()), Err(VtfxError::NoPalette)), "{}", context);
+    }
+}
+
+#[test]
+fn depth_formats_are_normalised_grayscale()
+{
+    for platform in PLATFORMS
+    {
+        //Big endian u16 depth of 0, 0x1234 and 0xFFFF
+        for format in [ImageFormat::IMAGE_FORMAT_X360_DST16, ImageFormat::IMAGE_FORMAT_NV_DST16, ImageFormat::IMAGE_FORMAT_ATI_DST16]
+        {
+            let image = decode(platform, format, 3, 1, &[0x00, 0x00, 0x12, 0x34, 0xFF, 0xFF]);
+            let luma = image.as_luma16().unwrap_or_else(|| panic!("{:?} {:?}: expected Luma16 image", platform, format));
+            assert_eq!(luma.as_raw(), &[0, 0x1234, u16::MAX], "{:?} {:?}", platform, format);
+        }
+
+        //24 bit depth of 0, 0x800000 and 0xFFFFFF in the upper bits, stencil (0xAB) is ignored
+        let data = [0x00, 0x00, 0x00, 0xAB, 0x80, 0x00, 0x00, 0xAB, 0xFF, 0xFF, 0xFF, 0xAB];
+        for format in [ImageFormat::IMAGE_FORMAT_X360_DST24, ImageFormat::IMAGE_FORMAT_NV_DST24, ImageFormat::IMAGE_FORMAT_ATI_DST24, ImageFormat::IMAGE_FORMAT_NV_INTZ, ImageFormat::IMAGE_FORMAT_NV_RAWZ]
+        {
+            let image = decode(platform, format, 3, 1, &data);
+            let luma = image.as_luma16().unwrap_or_else(|| panic!("{:?} {:?}: expected Luma16 image", platform, format));
+            assert_eq!(luma.as_raw(), &[0, 0x8000, u16::MAX], "{:?} {:?}", platform, format);
+        }
+
+        //20e4 float depth: 0.5, 0.75, 1.0 and 1.5
+        let data = [0xE0, 0x00, 0x00, 0xAB, 0xE8, 0x00, 0x00, 0xAB, 0xF0, 0x00, 0x00, 0xAB, 0xF8, 0x00, 0x00, 0xAB];
+        let image = decode(platform, ImageFormat::IMAGE_FORMAT_X360_DST24F, 4, 1, &data);
+        let rgba = image.as_rgba32f().unwrap_or_else(|| panic!("{:?} X360_DST24F: expected Rgba32F image", platform));
+        let depths: Vec<f32> = rgba.pixels().map(|pixel| pixel.0[0]).collect();
+        assert_eq!(depths, [0.5, 0.75, 1.0, 1.5], "{:?} X360_DST24F", platform);
+    }
+}
+
+#[test]
+fn packed_formats_are_big_endian_u16()
+{
+    //Red, green with no alpha, blue and yellow with no alpha. Formats without alpha are opaque
+    let with_alpha = [RED, [0x00, 0xFF, 0x00, 0x00], BLUE, [0xFF, 0xFF, 0x00, 0x00]];
+    let opaque = [RED, GREEN, BLUE, [0xFF, 0xFF, 0x00, 0xFF]];
+    let formats = [
+        (ImageFormat::IMAGE_FORMAT_RGB565, [0x00, 0x1F, 0x07, 0xE0, 0xF8, 0x00, 0x07, 0xFF], opaque),
+        (ImageFormat::IMAGE_FORMAT_BGR565, [0xF8, 0x00, 0x07, 0xE0, 0x00, 0x1F, 0xFF, 0xE0], opaque),
+        (ImageFormat::IMAGE_FORMAT_BGRA4444, [0xFF, 0x00, 0x00, 0xF0, 0xF0, 0x0F, 0x0F, 0xF0], with_alpha),
+        (ImageFormat::IMAGE_FORMAT_BGRX5551, [0x7C, 0x00, 0x03, 0xE0, 0x00, 0x1F, 0x7F, 0xE0], opaque),
+        (ImageFormat::IMAGE_FORMAT_BGRA5551, [0xFC, 0x00, 0x03, 0xE0, 0x80, 0x1F, 0x7F, 0xE0], with_alpha),
+        (ImageFormat::IMAGE_FORMAT_LINEAR_BGRX5551, [0x7C, 0x00, 0x03, 0xE0, 0x00, 0x1F, 0x7F, 0xE0], opaque),
+    ];
+
+    for platform in PLATFORMS
+    {
+        for (format, data, expected) in formats
+        {
+            let image = decode(platform, format, 4, 1, &data);
+            assert_rgba8(&image, &expected, &format!("{:?} {:?}", platform, format));
+        }
+    }
+}
+
+#[test]
+fn rgba16161616_is_big_endian_u16_per_channel()
+{
+    let data = [0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0, 0xFF, 0xFF, 0x00, 0x00, 0x01, 0x01, 0x80, 0x00];
+    let expected: [[u16; 4]; 2] = [[0x1234, 0x5678, 0x9ABC, 0xDEF0], [0xFFFF, 0, 0x0101, 0x8000]];
+
+    for platform in PLATFORMS
+    {
+        for format in [ImageFormat::IMAGE_FORMAT_RGBA16161616, ImageFormat::IMAGE_FORMAT_LINEAR_RGBA16161616]
+        {
+            let image = decode(platform, format, 2, 1, &data);
+            let rgba = image.as_rgba16().unwrap_or_else(|| panic!("{:?} {:?}: expected Rgba16 image", platform, format));
+            let actual: Vec<[u16; 4]> = rgba.pixels().map(|pixel| pixel.0).collect();
+            assert_eq!(actual, expected, "{:?} {:?}", platform, format);
+        }
+    }
+}
+
+#[test]
+fn luminance_and_two_channel_formats()
+{
+    for platform in PLATFORMS
+    {
+        for format in [ImageFormat::IMAGE_FORMAT_I8, ImageFormat::IMAGE_FORMAT_LINEAR_I8]
+        {
+            let image = decode(platform, format, 4, 1, &[0, 0x40, 0x80, 255]);
+            let luma = image.as_luma8().unwrap_or_else(|| panic!("{:?} {:?}: expected Luma8 image", platform, format));
+            assert_eq!(luma.as_raw(), &[0, 0x40, 0x80, 255], "{:?} {:?}", platform, format);
+        }
+
+        //Intensity then alpha
+        let image = decode(platform, ImageFormat::IMAGE_FORMAT_IA88, 2, 1, &[0x20, 0xF0, 0xC0, 0x10]);
+        let luma_alpha = image.as_luma_alpha8().unwrap_or_else(|| panic!("{:?} IA88: expected LumaA8 image", platform));
+        assert_eq!(luma_alpha.as_raw(), &[0x20, 0xF0, 0xC0, 0x10], "{:?} IA88", platform);
+
+        let image = decode(platform, ImageFormat::IMAGE_FORMAT_A8, 2, 1, &[0x20, 0xF0]);
+        assert_rgba8(&image, &[[0, 0, 0, 0x20], [0, 0, 0, 0xF0]], &format!("{:?} A8", platform));
+
+        //U then V, blue defaults to 0
+        let image = decode(platform, ImageFormat::IMAGE_FORMAT_UV88, 2, 1, &[0x20, 0xF0, 0xC0, 0x10]);
+        assert_rgba8(&image, &[[0x20, 0xF0, 0, 255], [0xC0, 0x10, 0, 255]], &format!("{:?} UV88", platform));
+    }
+}
+
+#[test]
+fn float_formats_are_big_endian()
+{
+    //0.5, 2.0, -1.0 and 0.25
+    let half_data = [0x38, 0x00, 0x40, 0x00, 0xBC, 0x00, 0x34, 0x00];
+    let float_data = [0x3F, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0xBF, 0x80, 0x00, 0x00, 0x3E, 0x80, 0x00, 0x00];
+    let formats: [(ImageFormat, &[u8], [f32; 4]); 4] = [
+        (ImageFormat::IMAGE_FORMAT_RGBA16161616F, &half_data, [0.5, 2.0, -1.0, 0.25]),
+        (ImageFormat::IMAGE_FORMAT_R32F, &float_data[0..4], [0.5, 0.0, 0.0, 1.0]),
+        (ImageFormat::IMAGE_FORMAT_RGB323232F, &float_data[0..12], [0.5, 2.0, -1.0, 1.0]),
+        (ImageFormat::IMAGE_FORMAT_RGBA32323232F, &float_data, [0.5, 2.0, -1.0, 0.25]),
+    ];
+
+    for platform in PLATFORMS
+    {
+        for (format, data, expected) in formats
+        {
+            let image = decode(platform, format, 1, 1, data);
+            let rgba = image.as_rgba32f().unwrap_or_else(|| panic!("{:?} {:?}: expected Rgba32F image", platform, format));
+            assert_eq!(rgba.get_pixel(0, 0).0, expected, "{:?} {:?}", platform, format);
+        }
+    }
+}
+
+///Bc1 block with red (0xF800) as colour 0 and blue (0x001F) as colour 1, only pixel (1, 0) uses colour 1
+const BC1_BLOCK: [u8; 8] = [0x00, 0xF8, 0x1F, 0x00, 0x04, 0x00, 0x00, 0x00];
+///BC1_BLOCK as stored on xbox 360, each u16 is big endian
+const BC1_BLOCK_X360: [u8; 8] = [0xF8, 0x00, 0x00, 0x1F, 0x00, 0x04, 0x00, 0x00];
+///Bc4 style block with endpoints 255 and 0, only pixel (1, 0) uses endpoint 1 (0)
+const BC4_BLOCK: [u8; 8] = [0xFF, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+const BC4_BLOCK_X360: [u8; 8] = [0x00, 0xFF, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00];
+///Bc4 style block with endpoints 0 and 255, only pixel (1, 0) uses endpoint 1 (255)
+const BC4_BLOCK_INVERTED: [u8; 8] = [0x00, 0xFF, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00];
+const BC4_BLOCK_INVERTED_X360: [u8; 8] = [0xFF, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00];
+///Bc2 explicit alpha where only pixel (1, 0) is opaque
+const BC2_ALPHA: [u8; 8] = [0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+const BC2_ALPHA_X360: [u8; 8] = [0x00, 0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+
+///Expected 4x4 block where pixel (1, 0) is second and every other pixel is first
+fn block_pixels(first: [u8; 4], second: [u8; 4]) -> Vec<[u8; 4]>
+{
+    (0..16).map(|pixel| match pixel { 1 => second, _ => first }).collect()
+}
+
+///Format, PS3 data, xbox 360 data and the expected pixels
+type DxtCase = (ImageFormat, Vec<u8>, Vec<u8>, Vec<[u8; 4]>);
+
+#[test]
+fn dxt_formats_are_u16_swapped_on_x360()
+{
+    //PS3 stores dxt blocks the same as PC
+    let formats: [DxtCase; 6] = [
+        (ImageFormat::IMAGE_FORMAT_DXT1, BC1_BLOCK.to_vec(), BC1_BLOCK_X360.to_vec(), block_pixels(RED, BLUE)),
+        (ImageFormat::IMAGE_FORMAT_DXT1_ONEBITALPHA, BC1_BLOCK.to_vec(), BC1_BLOCK_X360.to_vec(), block_pixels(RED, BLUE)),
+        (ImageFormat::IMAGE_FORMAT_DXT3, [BC2_ALPHA, BC1_BLOCK].concat(), [BC2_ALPHA_X360, BC1_BLOCK_X360].concat(), block_pixels([0xFF, 0x00, 0x00, 0x00], BLUE)),
+        (ImageFormat::IMAGE_FORMAT_DXT5, [BC4_BLOCK, BC1_BLOCK].concat(), [BC4_BLOCK_X360, BC1_BLOCK_X360].concat(), block_pixels(RED, [0x00, 0x00, 0xFF, 0x00])),
+        //Red is copied to green and blue
+        (ImageFormat::IMAGE_FORMAT_ATI1N, BC4_BLOCK.to_vec(), BC4_BLOCK_X360.to_vec(), block_pixels([0xFF; 4], [0x00, 0x00, 0x00, 0xFF])),
+        //Red then green, blue is 0 without z reconstruction
+        (ImageFormat::IMAGE_FORMAT_ATI2N, [BC4_BLOCK, BC4_BLOCK_INVERTED].concat(), [BC4_BLOCK_X360, BC4_BLOCK_INVERTED_X360].concat(), block_pixels(RED, GREEN)),
+    ];
+
+    for (format, ps3_data, x360_data, expected) in &formats
+    {
+        for (platform, data) in [(Platform::Ps3, ps3_data), (Platform::X360, x360_data)]
+        {
+            let image = decode(platform, *format, 4, 4, data);
+            assert_rgba8(&image, expected, &format!("{:?} {:?}", platform, format));
+        }
+    }
+}
+
+#[test]
+fn linear_formats_are_never_untiled()
+{
+    //A 32x32 gradient stored linearly, xbox 360 untiling by default must not scramble it
+    let colours: Vec<[u8; 4]> = (0..32 * 32).map(|pixel| [(pixel % 32 * 8) as u8, (pixel / 32 * 8) as u8, 0, 255]).collect();
+    //Stored as A,R,G,B
+    let data: Vec<u8> = (0..32 * 32).flat_map(|pixel| [255, (pixel % 32 * 8) as u8, (pixel / 32 * 8) as u8, 0]).collect();
+    let options = DecodeOptions { export_alpha: true, ..Default::default() };
+    let buffer = build_vtfx(Platform::X360, ImageFormat::IMAGE_FORMAT_LINEAR_BGRA8888, 32, 32, &data);
+
+    let image = decode_image(&buffer, &options).unwrap();
+    assert_rgba8(&image, &colours, "LINEAR_BGRA8888");
+}
+
+#[test]
+fn written_vtfx_uses_platform_byte_order()
+{
+    //Data is given in PC byte order, the writer must convert it the same way as it is read back.
+    //The ARGB8888 and RGBA8888 console orders are unverified, so for these this only checks the round trip
+    let formats: [(ImageFormat, Vec<u8>, Vec<[u8; 4]>); 5] = [
+        (ImageFormat::IMAGE_FORMAT_BGRA8888, vec![0x33, 0x22, 0x11, 0x44, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x80], ROW.to_vec()),
+        (ImageFormat::IMAGE_FORMAT_ARGB8888, vec![0x44, 0x11, 0x22, 0x33, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x80, 0x00, 0x00, 0xFF], ROW.to_vec()),
+        (ImageFormat::IMAGE_FORMAT_RGBA8888, vec![0x11, 0x22, 0x33, 0x44, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x80], ROW.to_vec()),
+        (ImageFormat::IMAGE_FORMAT_RGB888, vec![0x11, 0x22, 0x33, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF], ROW_OPAQUE.to_vec()),
+        (ImageFormat::IMAGE_FORMAT_DXT5, [BC4_BLOCK, BC1_BLOCK].concat(), block_pixels(RED, [0x00, 0x00, 0xFF, 0x00])),
+    ];
+
+    for (platform, vtfx_platform) in [(Platform::Ps3, VtfxPlatform::Ps3), (Platform::X360, VtfxPlatform::Xbox360)]
+    {
+        for (format, data, expected) in &formats
+        {
+            //Images are 4 pixels wide
+            let texture = PcTexture { flags: TEXTUREFLAGS_NOMIP, image_data: data.clone(), ..numbered_texture(4, (expected.len() / 4) as u16, *format, 1, 1) };
+
+            let mut buffer = Vec::new();
+            write_vtfx(&texture, vtfx_platform, &mut buffer).unwrap();
+            let options = DecodeOptions { export_alpha: true, ..Default::default() };
+            let image = decode_image(&buffer, &options).unwrap_or_else(|error| panic!("{:?} {:?} failed to decode: {}", platform, format, error));
+            assert_rgba8(&image, expected, &format!("written {:?} {:?}", platform, format));
+        }
+    }
+}
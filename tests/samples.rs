@@ -0,0 +1,51 @@
+//! Regression tests decoding the sample textures in ``test``.
+//! The xbox 360 samples are stored linearly so are decoded with untiling disabled.
+
+use image::RgbaImage;
+use vtfx_reader::{decode_image, DecodeOptions};
+
+fn decode_sample(name: &str) -> RgbaImage
+{
+    let buffer = std::fs::read(format!("{}/test/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap();
+    let options = DecodeOptions { export_alpha: true, no_untile: true, ..Default::default() };
+    decode_image(&buffer, &options).unwrap_or_else(|error| panic!("{} failed to decode: {}", name, error)).to_rgba8()
+}
+
+///Mean difference between the alpha of horizontally neighbouring pixels
+fn mean_alpha_difference(image: &RgbaImage) -> f64
+{
+    let mut total = 0u64;
+    let mut count = 0u64;
+    for row in image.rows()
+    {
+        let alpha: Vec<i32> = row.map(|pixel| pixel.0[3] as i32).collect();
+        for pair in alpha.windows(2)
+        {
+            total += pair[0].abs_diff(pair[1]) as u64;
+            count += 1;
+        }
+    }
+
+    total as f64 / count as f64
+}
+
+///Alpha at (0, 0), the centre, a quarter of the way in, the last pixel and (w / 3, 2h / 3)
+fn sample_alpha(image: &RgbaImage) -> [u8; 5]
+{
+    let (width, height) = image.dimensions();
+    [(0, 0), (width / 2, height / 2), (width / 4, height / 4), (width - 1, height - 1), (width / 3, height * 2 / 3)]
+        .map(|(x, y)| image.get_pixel(x, y).0[3])
+}
+
+#[test]
+fn x360_dxt5_alpha_is_smooth()
+{
+    //The alpha half of each block is u16 swapped like the colour half, leaving it unswapped gives noisy alpha (a mean difference of about 15)
+    let image = decode_sample("scout_head.360.vtf");
+    let difference = mean_alpha_difference(&image);
+    assert!(difference < 1.0, "mean alpha difference {}", difference);
+    assert_eq!(sample_alpha(&image), [0, 101, 64, 0, 101]);
+
+    let image = decode_sample("scout_blue.360.vtf");
+    assert_eq!(sample_alpha(&image), [0, 255, 0, 0, 255]);
+}